@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDate};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::benchmark::{at_dates, compare, BenchmarkComparison};
+use crate::config::{Config, Rebalance, Window};
+use crate::costs::Holdings;
+use crate::objective::{choose_split, minimum_acceptable, optimize};
+use crate::portfolio::{align, Portfolio, PortfolioStatistics, TimeSeries};
+use crate::selection::{apply_filters, keep_best};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RebalanceEvent {
+    pub date: NaiveDate,
+    pub weights: HashMap<String, f64>,
+    pub turnover: f64,
+    /// Fraction of the value of the portfolio paid in costs to trade to `weights`.
+    pub costs: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestMetrics {
+    pub value_at_end: f64,
+    pub annualized_returns: f64,
+    pub annualized_volatility: f64,
+    pub annualized_sharpe_ratio: Option<f64>,
+    pub max_drawdown: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BacktestReport {
+    pub dates: Vec<NaiveDate>,
+    pub returns: Vec<f64>,
+    pub equity_curve: Vec<f64>,
+    pub benchmark_equity_curve: Vec<f64>,
+    pub rebalances: Vec<RebalanceEvent>,
+    pub metrics: BacktestMetrics,
+    pub benchmark_metrics: BacktestMetrics,
+    /// Comparison benchmarks over the out-of-sample periods.
+    pub comparisons: Vec<BenchmarkComparison>,
+}
+
+/// Whether `current` starts a new month, quarter or year with respect to `previous`.
+pub fn is_rebalance_date(rebalance: Rebalance, previous: NaiveDate, current: NaiveDate) -> bool {
+    match rebalance {
+        Rebalance::Monthly => {
+            (previous.year(), previous.month()) != (current.year(), current.month())
+        }
+        Rebalance::Quarterly => {
+            (previous.year(), previous.month0() / 3) != (current.year(), current.month0() / 3)
+        }
+        Rebalance::Yearly => previous.year() != current.year(),
+    }
+}
+
+/// Half of the sum of the absolute changes in weights, i.e. the fraction of the
+/// portfolio that is sold (and bought back) when moving from `from` to `to`.
+pub fn turnover(from: &[f64], to: &[f64]) -> f64 {
+    from.iter().zip(to).map(|(a, b)| (a - b).abs()).sum::<f64>() / 2.0
+}
+
+/// Weights of `funds` chosen with only the data of `funds` themselves, as the outputs
+/// step does: filtering, keeping the best funds and optimizing the objective. Funds
+/// that were not selected get zero weight.
+fn allocate(funds: &[TimeSeries], risk_free: &TimeSeries, config: &Config) -> Result<Vec<f64>> {
+    let dates = align(funds)?;
+    let (from_date, to_date) = match (dates.first(), dates.last()) {
+        (Some(from_date), Some(to_date)) => (*from_date, *to_date),
+        _ => return Err(anyhow!("No periods to allocate on")),
+    };
+
+    let selection = apply_filters(funds.to_vec(), &config.funds_filters, from_date, to_date)?;
+    let selected = keep_best(selection, config.portfolio.number_of_funds).selected;
+
+    if selected.is_empty() {
+        return Err(anyhow!(
+            "No funds remain after filtering the window from {} to {}",
+            from_date,
+            to_date
+        ));
+    }
+
+    let statistics = PortfolioStatistics::new(&selected, risk_free)?;
+    let candidates = optimize(&statistics, config.optimizer.frontier_points);
+    let minimum_acceptable = minimum_acceptable(
+        &config.objective,
+        risk_free,
+        &statistics.dates,
+        statistics.frequency,
+    )?;
+
+    let split = choose_split(
+        &selected,
+        risk_free,
+        &minimum_acceptable,
+        config,
+        &candidates.max_sharpe,
+        &candidates.all(),
+    )?
+    .ok_or(anyhow!(
+        "No split satisfies the maximum CVaR of {} in the window from {} to {}, searching \
+        only the candidates of the efficient frontier",
+        config.risk.max_cvar.unwrap_or_default(),
+        from_date,
+        to_date
+    ))?;
+
+    Ok(funds
+        .iter()
+        .map(|f| {
+            selected
+                .iter()
+                .position(|s| s.id == f.id)
+                .map_or(0.0, |i| split[i])
+        })
+        .collect())
+}
+
+fn metrics(ts: &TimeSeries, risk_free: Option<&TimeSeries>) -> Result<BacktestMetrics> {
+    let p = Portfolio::new(std::slice::from_ref(ts), &[1.0])?;
+
+    Ok(BacktestMetrics {
+        value_at_end: p.calculate_value_at_end(1.0),
+        annualized_returns: p.annualized_returns(),
+        annualized_volatility: p.annualized_volatility(),
+        annualized_sharpe_ratio: risk_free
+            .map(|rf| p.annualized_sharpe_ratio(rf))
+            .transpose()?,
+        max_drawdown: p.max_drawdown(),
+    })
+}
+
+/// Return of the fund held with `weight` at `date`, which may only be missing when
+/// nothing of it is held, up to the rounding of the sales.
+fn held_return(fund: &TimeSeries, weight: f64, date: NaiveDate) -> Result<f64> {
+    match fund.dates.binary_search(&date) {
+        Ok(i) => Ok(fund.returns[i]),
+        Err(_) if weight < 1e-12 => Ok(0.0),
+        Err(_) => Err(anyhow!(
+            "Fund '{}' is held in the backtest but has no return at {}",
+            fund.id,
+            date
+        )),
+    }
+}
+
+/// Walk-forward backtest: at each rebalance date, weights are chosen using only the
+/// `lookback` previous periods (or all of them, for an expanding window) and then
+/// held, drifting with the returns of each fund, until the next rebalance date. The
+/// trades of each rebalance, as well as the redemption at the end, pay `config.costs`.
+///
+/// Funds may cover different periods: only the ones with returns over the whole window
+/// and at the rebalance date are candidates at each rebalance.
+pub fn run(
+    funds: &[TimeSeries],
+    risk_free: &TimeSeries,
+    comparisons: &[TimeSeries],
+    config: &Config,
+) -> Result<BacktestReport> {
+    let settings = &config.backtest;
+
+    if !funds.iter().map(|f| f.frequency).all_equal() {
+        return Err(anyhow!(
+            "Funds with different frequencies can't be backtested together"
+        ));
+    }
+
+    let frequency = funds.first().map(|f| f.frequency).unwrap_or_default();
+    let dates = funds
+        .iter()
+        .flat_map(|f| f.dates.iter().copied())
+        .sorted()
+        .dedup()
+        .collect_vec();
+
+    if settings.lookback < 2 || dates.len() <= settings.lookback {
+        return Err(anyhow!(
+            "Backtest needs a lookback of at least 2 periods and more periods than it, but has a lookback of {} and {} periods",
+            settings.lookback,
+            dates.len()
+        ));
+    }
+
+    let costs = &config.costs;
+    let mut holdings = Holdings::new(funds.iter().map(|f| f.id.clone()).collect());
+    let mut value = costs.initial_investment;
+    let mut returns = Vec::new();
+    let mut rebalances = Vec::new();
+
+    for t in settings.lookback..dates.len() {
+        if t == settings.lookback || is_rebalance_date(settings.rebalance, dates[t - 1], dates[t]) {
+            let start = match settings.window {
+                Window::Rolling => t - settings.lookback,
+                Window::Expanding => 0,
+            };
+
+            let (candidates, window): (Vec<usize>, Vec<TimeSeries>) = funds
+                .iter()
+                .enumerate()
+                .filter(|(_, f)| f.returns_at(&dates[start..=t]).is_ok())
+                .map(|(i, f)| Ok((i, f.slice(dates[start], dates[t - 1])?)))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .unzip();
+
+            if window.is_empty() {
+                return Err(anyhow!(
+                    "No fund has returns over the window from {} to {}",
+                    dates[start],
+                    dates[t]
+                ));
+            }
+
+            let mut target = vec![0.0; funds.len()];
+            for (i, weight) in candidates.iter().zip(allocate(&window, risk_free, config)?) {
+                target[*i] = weight;
+            }
+
+            // The first allocation buys the whole portfolio.
+            let (turnover, cash) = if rebalances.is_empty() {
+                (1.0, costs.initial_investment)
+            } else {
+                (turnover(&holdings.weights(), &target), 0.0)
+            };
+
+            let before = holdings.value() + cash;
+            holdings.rebalance(&target, cash, t, costs);
+
+            rebalances.push(RebalanceEvent {
+                date: dates[t],
+                weights: funds
+                    .iter()
+                    .map(|f| f.id.to_string())
+                    .zip(target.iter().copied())
+                    .collect(),
+                turnover,
+                costs: 1.0 - holdings.value() / before,
+            });
+        }
+
+        let period_returns = funds
+            .iter()
+            .zip(holdings.weights())
+            .map(|(f, weight)| held_return(f, weight, dates[t]))
+            .collect::<Result<Vec<_>>>()?;
+        holdings.apply_returns(&period_returns);
+
+        let value_at_end = if t + 1 == dates.len() {
+            holdings.liquidate(dates.len(), costs)
+        } else {
+            holdings.value()
+        };
+
+        returns.push(value_at_end / value - 1.0);
+        value = value_at_end;
+    }
+
+    let out_of_sample = dates[settings.lookback..].to_vec();
+
+    let ts = TimeSeries::new(
+        "_backtest".to_string(),
+        out_of_sample.clone(),
+        returns.clone(),
+    )?
+    .with_frequency(frequency);
+    let benchmark = at_dates(risk_free, &out_of_sample)?;
+    let comparisons = compare(comparisons, &out_of_sample)?;
+
+    Ok(BacktestReport {
+        dates: out_of_sample,
+        returns,
+        equity_curve: ts.equity_curve(1.0),
+        benchmark_equity_curve: benchmark.equity_curve(1.0),
+        rebalances,
+        metrics: metrics(&ts, Some(risk_free))?,
+        benchmark_metrics: metrics(&benchmark, None)?,
+        comparisons,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::monthly_from;
+
+    fn date(y: i32, m: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, 1).unwrap()
+    }
+
+    #[test]
+    fn rebalance_dates() {
+        assert!(is_rebalance_date(
+            Rebalance::Monthly,
+            date(2021, 1),
+            date(2021, 2)
+        ));
+        assert!(!is_rebalance_date(
+            Rebalance::Quarterly,
+            date(2021, 1),
+            date(2021, 3)
+        ));
+        assert!(is_rebalance_date(
+            Rebalance::Quarterly,
+            date(2021, 3),
+            date(2021, 4)
+        ));
+        assert!(!is_rebalance_date(
+            Rebalance::Yearly,
+            date(2021, 3),
+            date(2021, 12)
+        ));
+        assert!(is_rebalance_date(
+            Rebalance::Yearly,
+            date(2021, 12),
+            date(2022, 1)
+        ));
+    }
+
+    #[test]
+    fn funds_launched_later_join_once_they_cover_the_window() {
+        let mut config = crate::config::get_config();
+        config.backtest.window = Window::Rolling;
+        config.backtest.lookback = 3;
+        config.backtest.rebalance = Rebalance::Monthly;
+        config.risk.max_cvar = None;
+
+        let old = [
+            0.01, 0.02, -0.01, 0.015, 0.0, 0.01, 0.02, -0.005, 0.01, 0.01, 0.0, 0.02,
+        ];
+        let funds = [
+            monthly_from("11.111.111/0001-91", (2021, 1), old.to_vec()),
+            monthly_from(
+                "22.222.222/0001-91",
+                (2021, 7),
+                vec![0.02, 0.01, 0.03, 0.0, 0.02, 0.01],
+            ),
+        ];
+        let risk_free = monthly_from("cdi", (2021, 1), vec![0.005; 12]);
+
+        let report = run(&funds, &risk_free, &[], &config).unwrap();
+
+        assert_eq!(report.dates.first(), Some(&date(2021, 4)));
+        assert_eq!(report.returns.len(), 9);
+
+        let weight = |event: &RebalanceEvent| event.weights["22.222.222/0001-91"];
+        let (before, after): (Vec<_>, Vec<_>) = report
+            .rebalances
+            .iter()
+            .partition(|e| e.date < date(2021, 10));
+
+        assert!(before.iter().all(|e| weight(e) == 0.0));
+        assert!(after.iter().any(|e| weight(e) > 0.0));
+    }
+
+    #[test]
+    fn turnover_of_weight_changes() {
+        assert!((turnover(&[0.5, 0.5, 0.0], &[0.2, 0.5, 0.3]) - 0.3).abs() < 1e-12);
+        assert_eq!(turnover(&[0.5, 0.5], &[0.5, 0.5]), 0.0);
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::daily_factor;
+use crate::id::SeriesId;
+use crate::portfolio::{Frequency, TimeSeries};
+
+/// Annualized Selic above which the savings account pays a fixed rate.
+const POUPANCA_SELIC_THRESHOLD: f64 = 0.085;
+const POUPANCA_FIXED_MONTHLY_RATE: f64 = 0.005;
+const POUPANCA_SELIC_FRACTION: f64 = 0.7;
+
+/// Month since which the savings account follows the Selic, by Law 12,703 of 2012.
+fn poupanca_rule_start() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2012, 5, 1).expect("Date is valid")
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Benchmarks {
+    /// Id of the series used as the risk-free rate.
+    pub risk_free: String,
+    /// Ids of the series compared with the portfolio in every plot and report.
+    #[serde(default)]
+    pub comparisons: Vec<String>,
+}
+
+/// Statistics of a benchmark over the periods of the portfolio it is compared with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkComparison {
+    pub id: SeriesId,
+    pub equity_curve: Vec<f64>,
+    pub expected_returns_at_end: f64,
+    pub average: f64,
+    pub volatility: f64,
+    pub annualized_returns: f64,
+    pub annualized_volatility: f64,
+    pub max_drawdown: f64,
+}
+
+fn find(all: &[TimeSeries], id: &str) -> Result<TimeSeries> {
+    all.iter().find(|b| b.id == id).cloned().ok_or_else(|| {
+        anyhow!(
+            "Benchmark '{}' has no time series. Available benchmarks: {:?}",
+            id,
+            all.iter().map(|b| &b.id).collect::<Vec<_>>()
+        )
+    })
+}
+
+impl Benchmarks {
+    pub fn risk_free(&self, all: &[TimeSeries]) -> Result<TimeSeries> {
+        find(all, &self.risk_free)
+    }
+
+    pub fn comparisons(&self, all: &[TimeSeries]) -> Result<Vec<TimeSeries>> {
+        self.comparisons.iter().map(|id| find(all, id)).collect()
+    }
+}
+
+/// Savings account (poupança) computed from the Selic with the rule in force since May
+/// 2012: 0.5% a month while the annualized Selic is above 8.5%, and 70% of it otherwise.
+/// Before that, it paid 0.5% a month regardless of the Selic. The reference rate (TR)
+/// added on top of both is left out, as it has been close to zero.
+pub fn poupanca(selic: &TimeSeries) -> Result<TimeSeries> {
+    let periods_per_year = selic.frequency.periods_per_year();
+
+    let returns = selic
+        .dates
+        .iter()
+        .zip(&selic.returns)
+        .map(|(date, r)| {
+            let annual = (1.0 + r).powf(periods_per_year) - 1.0;
+            let annual = if *date < poupanca_rule_start() || annual > POUPANCA_SELIC_THRESHOLD {
+                (1.0 + POUPANCA_FIXED_MONTHLY_RATE).powi(12) - 1.0
+            } else {
+                POUPANCA_SELIC_FRACTION * annual
+            };
+
+            let factor = match selic.frequency {
+                Frequency::Daily => daily_factor(annual),
+                _ => (1.0 + annual).powf(1.0 / periods_per_year),
+            };
+
+            factor - 1.0
+        })
+        .collect();
+
+    Ok(
+        TimeSeries::new("poupanca".to_string(), selic.dates.clone(), returns)?
+            .with_frequency(selic.frequency),
+    )
+}
+
+/// `benchmark` restricted to `dates`, failing if any of them is missing.
+pub fn at_dates(benchmark: &TimeSeries, dates: &[NaiveDate]) -> Result<TimeSeries> {
+    Ok(TimeSeries::new(
+        benchmark.id.clone(),
+        dates.to_vec(),
+        benchmark.returns_at(dates)?,
+    )?
+    .with_frequency(benchmark.frequency))
+}
+
+pub fn compare(benchmarks: &[TimeSeries], dates: &[NaiveDate]) -> Result<Vec<BenchmarkComparison>> {
+    benchmarks
+        .iter()
+        .map(|b| {
+            let ts = at_dates(b, dates)?;
+
+            Ok(BenchmarkComparison {
+                id: ts.id.clone(),
+                equity_curve: ts.equity_curve(1.0),
+                expected_returns_at_end: ts.calculate_value_at_end(1.0),
+                average: ts.average_returns(),
+                volatility: ts.std_returns(),
+                annualized_returns: ts.annualized_returns(),
+                annualized_volatility: ts.annualized_volatility(),
+                max_drawdown: ts.max_drawdown(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::monthly;
+
+    #[test]
+    fn poupanca_rule() {
+        // Roughly 13.75% and 2% a year.
+        let selic = monthly("selic", vec![0.0108, 0.00165]);
+        let poupanca = poupanca(&selic).unwrap();
+
+        assert!((poupanca.returns[0] - 0.005).abs() < 1e-12);
+
+        let annual = 1.00165_f64.powi(12) - 1.0;
+        let expected = (1.0 + 0.7 * annual).powf(1.0 / 12.0) - 1.0;
+        assert!((poupanca.returns[1] - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn poupanca_before_2012() {
+        // Roughly 2% a year, which would pay less than 0.5% a month under the new rule.
+        let dates = vec![
+            NaiveDate::from_ymd_opt(2012, 4, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2012, 5, 1).unwrap(),
+        ];
+        let selic = TimeSeries::new("selic".to_string(), dates, vec![0.00165; 2]).unwrap();
+        let poupanca = poupanca(&selic).unwrap();
+
+        assert!((poupanca.returns[0] - 0.005).abs() < 1e-12);
+        assert!(poupanca.returns[1] < 0.005);
+    }
+
+    #[test]
+    fn compares_over_dates() {
+        let ipca = monthly("ipca", vec![0.01, 0.02, 0.03]);
+        let dates = ipca.dates[1..].to_vec();
+
+        let comparison = compare(std::slice::from_ref(&ipca), &dates).unwrap();
+
+        assert_eq!(comparison[0].equity_curve.len(), 2);
+        assert!((comparison[0].expected_returns_at_end - 1.02 * 1.03).abs() < 1e-12);
+
+        let missing = vec![NaiveDate::from_ymd_opt(2022, 1, 1).unwrap()];
+        assert!(compare(&[ipca], &missing).is_err());
+    }
+
+    #[test]
+    fn selects_by_id() {
+        let all = vec![monthly("cdi", vec![0.01]), monthly("ipca", vec![0.005])];
+        let benchmarks = Benchmarks {
+            risk_free: "cdi".to_string(),
+            comparisons: vec!["ipca".to_string()],
+        };
+
+        assert_eq!(benchmarks.risk_free(&all).unwrap().id, "cdi");
+        assert_eq!(benchmarks.comparisons(&all).unwrap()[0].id, "ipca");
+
+        let unknown = Benchmarks {
+            risk_free: "selic".to_string(),
+            comparisons: Vec::new(),
+        };
+        assert!(unknown.risk_free(&all).is_err());
+    }
+}
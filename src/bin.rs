@@ -1,3 +1,5 @@
+pub mod backtest;
 pub mod outputs;
+pub mod planning;
 pub mod preprocess;
 pub mod timeseries;
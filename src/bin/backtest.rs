@@ -0,0 +1,85 @@
+use anyhow::Result;
+use plotly::{
+    common::{AxisSide, DashType, Line, Mode},
+    layout::Axis,
+    Bar, Layout, Plot, Scatter,
+};
+use std::path::Path;
+
+use investments::{backtest::run, config::get_config, portfolio::AllTimeSeries};
+
+pub fn main() -> Result<()> {
+    let config = get_config();
+    let funds = AllTimeSeries::load(Path::new("data/03_timeseries/models.json"))?.timeseries;
+    let benchmarks =
+        AllTimeSeries::load(Path::new("data/03_timeseries/benchmarks.json"))?.timeseries;
+    let risk_free = config.benchmarks.risk_free(&benchmarks)?;
+    let comparisons = config.benchmarks.comparisons(&benchmarks)?;
+
+    let report = run(&funds, &risk_free, &comparisons, &config)?;
+
+    let dates = report
+        .dates
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>();
+
+    let equity_curve = Scatter::new(dates.clone(), report.equity_curve.clone())
+        .mode(Mode::Lines)
+        .name("Backtest");
+    let benchmark_equity_curve = Scatter::new(dates.clone(), report.benchmark_equity_curve.clone())
+        .mode(Mode::Lines)
+        .name(format!("{} (risk-free)", risk_free.id));
+
+    let turnover = Bar::new(
+        report
+            .rebalances
+            .iter()
+            .map(|r| r.date.to_string())
+            .collect(),
+        report.rebalances.iter().map(|r| r.turnover).collect(),
+    )
+    .name("Turnover")
+    .opacity(0.3)
+    .y_axis("y2");
+
+    let mut plot = Plot::new();
+
+    plot.add_trace(equity_curve);
+    plot.add_trace(benchmark_equity_curve);
+
+    for comparison in &report.comparisons {
+        let scatter = Scatter::new(dates.clone(), comparison.equity_curve.clone())
+            .mode(Mode::Lines)
+            .line(Line::new().dash(DashType::Dot))
+            .name(comparison.id.to_string());
+        plot.add_trace(scatter);
+    }
+
+    plot.add_trace(turnover);
+    let layout = Layout::new()
+        .title("<b>Out-of-sample backtest</b>".into())
+        .y_axis(Axis::new().title("Value".into()))
+        .y_axis2(
+            Axis::new()
+                .title("Turnover".into())
+                .overlaying("y")
+                .side(AxisSide::Right),
+        );
+    plot.set_layout(layout);
+
+    let html = plot.to_html();
+
+    let path = Path::new("data/04_visualization/backtest.html");
+    std::fs::write(path, html)?;
+
+    // let path = Path::new("data/04_visualization/backtest.png");
+    // plot.write_image(path, plotly::ImageFormat::PNG, 1920, 1080, 1.0);
+
+    let jsonified_report = serde_json::to_string(&report)?;
+    let path = Path::new("data/05_output/backtest.json");
+
+    std::fs::write(path, jsonified_report)?;
+
+    Ok(())
+}
@@ -1,15 +1,27 @@
-use anyhow::Result;
-use geo::{ConvexHull, MultiPoint, Point};
+use anyhow::{anyhow, Result};
 use indicatif::ProgressBar;
 use itertools::Itertools;
-use ordered_float::OrderedFloat;
-use plotly::{common::Mode, Layout, Plot, Scatter};
+use plotly::{
+    common::{AxisSide, DashType, Fill, Line, Mode},
+    layout::Axis,
+    Layout, Plot, Scatter,
+};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::Path};
 
 use investments::{
-    config::get_config,
-    portfolio::{AllTimeSeries, Portfolio, TimeSeries},
+    benchmark::{at_dates, compare, BenchmarkComparison},
+    cashflow::Projection,
+    config::{get_config, Config},
+    fii::load_dividends,
+    frontier::{extract_efficient_frontier, EfficientFrontier, GridValidation},
+    id::SeriesId,
+    montecarlo::{report as monte_carlo_report, sample_paths},
+    objective::{choose_split, minimum_acceptable, optimize, Candidates},
+    portfolio::{AllTimeSeries, Drawdown, Portfolio, PortfolioStatistics, TimeSeries},
+    risk::RiskReport,
+    selection::{apply_filters, keep_best},
+    simulation::{simulate, RebalancePolicy, Simulation},
 };
 
 struct PossibleSplits {
@@ -18,6 +30,13 @@ struct PossibleSplits {
 }
 
 impl PossibleSplits {
+    fn from_splits(splits: &[Vec<f64>]) -> PossibleSplits {
+        PossibleSplits {
+            possible_splits: splits.iter().flatten().copied().collect(),
+            split_len: splits.first().map_or(1, |x| x.len()),
+        }
+    }
+
     fn iterate_over_splits(&self) -> impl Iterator<Item = &[f64]> {
         let mut idx = 0;
 
@@ -43,35 +62,73 @@ struct Statistics {
 }
 
 #[derive(Serialize, Deserialize)]
-struct Allocation {
-    allocations: HashMap<String, f64>,
-    sharpe_ratio: f64,
+struct RebalancingComparison {
+    #[serde(flatten)]
+    simulation: Simulation,
     expected_returns_at_end: f64,
-    average: f64,
-    volatility: f64,
+    gross_expected_returns_at_end: f64,
+    max_drawdown: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct RebalancingReport {
+    policies: Vec<RebalancingComparison>,
+    benchmarks: Vec<BenchmarkComparison>,
 }
 
-fn load_timeseries() -> Result<Vec<TimeSeries>> {
-    let path = Path::new("data/03_timeseries/models.json");
-    let timeseries = std::fs::read_to_string(path)?;
+#[derive(Serialize, Deserialize)]
+struct BenchmarkProjection {
+    id: SeriesId,
+    #[serde(flatten)]
+    projection: Projection,
+}
 
-    let all_ts: AllTimeSeries = serde_json::from_str(&timeseries)?;
+#[derive(Serialize, Deserialize)]
+struct CashFlowsReport {
+    #[serde(flatten)]
+    portfolio: Projection,
+    /// Same cash flows invested in each comparison benchmark instead.
+    benchmarks: Vec<BenchmarkProjection>,
+}
 
-    Ok(all_ts.timeseries)
+#[derive(Serialize, Deserialize)]
+struct NetOfCosts {
+    expected_returns_at_end: f64,
+    annualized_returns: f64,
+    annualized_sharpe_ratio: f64,
+    costs_at_end: f64,
 }
 
-fn load_cdi() -> Result<TimeSeries> {
-    let path = Path::new("data/03_timeseries/cdi.json");
-    let timeseries = std::fs::read_to_string(path)?;
+#[derive(Serialize, Deserialize)]
+struct AfterTax {
+    expected_returns_at_end: f64,
+    annualized_returns: f64,
+}
 
-    Ok(serde_json::from_str(&timeseries)?)
+#[derive(Serialize, Deserialize)]
+struct Allocation {
+    allocations: HashMap<String, f64>,
+    sharpe_ratio: f64,
+    expected_returns_at_end: f64,
+    average: f64,
+    volatility: f64,
+    annualized_returns: f64,
+    annualized_volatility: f64,
+    annualized_sharpe_ratio: f64,
+    sortino_ratio: f64,
+    calmar_ratio: f64,
+    omega_ratio: f64,
+    drawdown: Drawdown,
+    risk: Vec<RiskReport>,
+    net_of_costs: NetOfCosts,
+    after_tax: AfterTax,
+    benchmarks: Vec<BenchmarkComparison>,
 }
 
-fn get_possible_splits() -> PossibleSplits {
+fn get_possible_splits(n_funds: usize) -> PossibleSplits {
     let config = get_config();
 
     let min_gran = config.portfolio.split_granularity;
-    let n_funds = config.portfolio.number_of_funds;
 
     let total = (1.0 / min_gran).round() as usize;
 
@@ -81,10 +138,7 @@ fn get_possible_splits() -> PossibleSplits {
 
     let mut possible_splits = Vec::with_capacity(granularity.len().pow((n_funds - 1) as u32));
 
-    for mut split in std::iter::repeat(granularity)
-        .take(n_funds - 1)
-        .multi_cartesian_product()
-    {
+    for mut split in std::iter::repeat_n(granularity, n_funds - 1).multi_cartesian_product() {
         let s = split.iter().sum::<f64>();
 
         if s <= 1.0 {
@@ -101,8 +155,7 @@ fn get_possible_splits() -> PossibleSplits {
 }
 
 fn get_statistics_from_splits(
-    risk_free: &TimeSeries,
-    funds: &[TimeSeries],
+    portfolio_statistics: &PortfolioStatistics,
     possible_splits: PossibleSplits,
 ) -> Statistics {
     let mut splits = Vec::new();
@@ -117,14 +170,10 @@ fn get_statistics_from_splits(
         (possible_splits.possible_splits.len() / possible_splits.split_len) as u64,
     );
     for possible_split in possible_splits_iter {
-        let p = Portfolio::new(funds, possible_split).expect(
-            "Number of funds and splits should have the same length when building Portfolio",
-        );
-
-        volatilities.push(p.std());
-        average_returns.push(p.average());
-        returns_at_end.push(p.calculate_value_at_end(1.0));
-        sharpe_ratios.push(p.sharpe_ratio(risk_free));
+        volatilities.push(portfolio_statistics.std(possible_split));
+        average_returns.push(portfolio_statistics.average(possible_split));
+        returns_at_end.push(portfolio_statistics.calculate_value_at_end(possible_split, 1.0));
+        sharpe_ratios.push(portfolio_statistics.sharpe_ratio(possible_split));
         splits.push(possible_split.to_vec());
         pb.inc(1);
     }
@@ -139,137 +188,352 @@ fn get_statistics_from_splits(
     }
 }
 
-fn build_splits_hashmap<'a>(
-    xs: &'a [f64],
-    ys: &'a [f64],
-    splits: &'a [Vec<f64>],
-) -> HashMap<(OrderedFloat<f64>, OrderedFloat<f64>), &'a Vec<f64>> {
-    let mut hashmap = HashMap::new();
-
-    for i in 0..xs.len() {
-        hashmap.insert((OrderedFloat(xs[i]), OrderedFloat(ys[i])), &splits[i]);
-    }
-
-    hashmap
+fn hover_text(portfolio_statistics: &PortfolioStatistics, split: &[f64]) -> String {
+    format!(
+        "Split: {:.2?}<br>Annualized return: {:.2}%<br>Annualized volatility: {:.2}%<br>Annualized Sharpe ratio: {:.2}",
+        split,
+        100.0 * portfolio_statistics.annualized_returns(split),
+        100.0 * portfolio_statistics.annualized_volatility(split),
+        portfolio_statistics.annualized_sharpe_ratio(split),
+    )
 }
 
-fn recover_splits<'a>(
-    hm: &'a HashMap<(OrderedFloat<f64>, OrderedFloat<f64>), &'a Vec<f64>>,
-    xs: &'a [f64],
-    ys: &'a [f64],
-) -> Vec<&'a Vec<f64>> {
-    let mut splits = Vec::new();
+fn build_allocation(
+    config: &Config,
+    risk_free: &TimeSeries,
+    minimum_acceptable: &TimeSeries,
+    comparisons: &[TimeSeries],
+    funds: &[TimeSeries],
+    dividends: &[TimeSeries],
+    split: &[f64],
+) -> Result<Allocation> {
+    let p = Portfolio::new(funds, split)?;
+    let net = Portfolio::with_policy(funds, split, RebalancePolicy::ConstantMix, &config.costs)?;
+    let after_tax = Portfolio::after_tax(funds, split, &config.taxes, dividends)?;
 
-    for (x, y) in xs.iter().zip(ys) {
-        splits.push(hm[&(OrderedFloat(*x), OrderedFloat(*y))])
-    }
+    let allocations = HashMap::from_iter(
+        funds
+            .iter()
+            .map(|f| f.id.to_string())
+            .zip(split.iter().copied()),
+    );
 
-    splits
+    Ok(Allocation {
+        allocations,
+        average: p.average(),
+        expected_returns_at_end: p.calculate_value_at_end(1.0),
+        sharpe_ratio: p.sharpe_ratio(risk_free)?,
+        volatility: p.std(),
+        annualized_returns: p.annualized_returns(),
+        annualized_volatility: p.annualized_volatility(),
+        annualized_sharpe_ratio: p.annualized_sharpe_ratio(risk_free)?,
+        sortino_ratio: p.sortino_ratio(minimum_acceptable)?,
+        calmar_ratio: p.calmar_ratio(),
+        omega_ratio: p.omega_ratio(minimum_acceptable)?,
+        drawdown: p.drawdown(),
+        risk: p.risk_reports(&config.risk.confidence_levels),
+        net_of_costs: NetOfCosts {
+            expected_returns_at_end: net.calculate_value_at_end(1.0),
+            annualized_returns: net.annualized_returns(),
+            annualized_sharpe_ratio: net.annualized_sharpe_ratio(risk_free)?,
+            costs_at_end: p.calculate_value_at_end(1.0) - net.calculate_value_at_end(1.0),
+        },
+        after_tax: AfterTax {
+            expected_returns_at_end: after_tax.calculate_value_at_end(1.0),
+            annualized_returns: after_tax.annualized_returns(),
+        },
+        benchmarks: compare(comparisons, p.dates())?,
+    })
 }
 
 fn get_best_funds() -> Result<Vec<TimeSeries>> {
-    let mut funds = load_timeseries()?;
-
-    funds.sort_by(|t1, t2| {
-        t1.average_returns()
-            .partial_cmp(&t2.average_returns())
-            .expect("No NaNs should exist for ordering.")
-    });
-    funds.reverse();
+    let funds = AllTimeSeries::load(Path::new("data/03_timeseries/models.json"))?.timeseries;
 
     let config = get_config();
+    let (from_date, to_date) = config.portfolio.window()?;
     let n = config.portfolio.number_of_funds;
 
-    Ok(funds[0..n].to_vec())
+    let selection = apply_filters(funds, &config.funds_filters, from_date, to_date)?;
+    let selection = keep_best(selection, n);
+
+    let jsonified_selection = serde_json::to_string(&selection.report())?;
+    let path = Path::new("data/05_output/fund_selection.json");
+
+    std::fs::write(path, jsonified_selection)?;
+
+    if selection.selected.is_empty() {
+        return Err(anyhow!(
+            "No funds remain after filtering. See the reasons in 'fund_selection.json'"
+        ));
+    }
+
+    if selection.selected.len() < n {
+        eprintln!(
+            "Only {} funds remain after filtering, fewer than the {} requested",
+            selection.selected.len(),
+            n
+        );
+    }
+
+    Ok(selection.selected)
 }
 
 pub fn main() -> Result<()> {
+    let config = get_config();
     let funds = get_best_funds()?;
-    let cdi = load_cdi()?;
-    let possible_splits = get_possible_splits();
+    let benchmarks =
+        AllTimeSeries::load(Path::new("data/03_timeseries/benchmarks.json"))?.timeseries;
+    let risk_free = config.benchmarks.risk_free(&benchmarks)?;
+    let comparisons = config.benchmarks.comparisons(&benchmarks)?;
+
+    let portfolio_statistics = PortfolioStatistics::new(&funds, &risk_free)?;
+    let dividends = load_dividends(
+        Path::new("data/02_preprocessed/fii.csv"),
+        portfolio_statistics.frequency,
+    )?;
+    let benchmark_comparisons = compare(&comparisons, &portfolio_statistics.dates)?;
+
+    let minimum_acceptable = minimum_acceptable(
+        &config.objective,
+        &risk_free,
+        &portfolio_statistics.dates,
+        portfolio_statistics.frequency,
+    )?;
+
+    let Candidates {
+        max_sharpe: max_sharpe_split,
+        min_variance: min_variance_split,
+        frontier,
+    } = optimize(&portfolio_statistics, config.optimizer.frontier_points);
+
+    let max_sharpe_allocation = build_allocation(
+        &config,
+        &risk_free,
+        &minimum_acceptable,
+        &comparisons,
+        &funds,
+        &dividends,
+        &max_sharpe_split,
+    )?;
+    let min_variance_allocation = build_allocation(
+        &config,
+        &risk_free,
+        &minimum_acceptable,
+        &comparisons,
+        &funds,
+        &dividends,
+        &min_variance_split,
+    )?;
+
+    let frontier_statistics = get_statistics_from_splits(
+        &portfolio_statistics,
+        PossibleSplits::from_splits(&frontier),
+    );
 
-    let statistics = get_statistics_from_splits(&cdi, &funds, possible_splits);
-    let splits_as_text = statistics
-        .splits
-        .iter()
-        .map(|x| format!("Split: {:.2?}", x))
-        .collect::<Vec<_>>();
+    let grid_statistics = config.optimizer.validate_with_grid.then(|| {
+        get_statistics_from_splits(&portfolio_statistics, get_possible_splits(funds.len()))
+    });
 
-    // Efficient Frontier
+    let grid_validation = grid_statistics.as_ref().map(|g| GridValidation {
+        grid_sharpe_ratio: g.sharpe_ratios.iter().copied().fold(f64::MIN, f64::max),
+        optimizer_sharpe_ratio: max_sharpe_allocation.sharpe_ratio,
+    });
 
-    let scatter = Scatter::new(
-        statistics.volatilities.clone(),
-        statistics.average_returns.clone(),
-    )
-    .mode(Mode::Markers)
-    .hover_text_array(splits_as_text.clone());
+    let mut volatilities = frontier_statistics.volatilities.clone();
+    let mut averages = frontier_statistics.average_returns.clone();
+    let mut splits = frontier_statistics.splits.clone();
 
-    let mut plot = Plot::new();
+    if let Some(grid_statistics) = &grid_statistics {
+        volatilities.extend(&grid_statistics.volatilities);
+        averages.extend(&grid_statistics.average_returns);
+        splits.extend(grid_statistics.splits.iter().cloned());
+    }
 
-    plot.add_trace(scatter);
-    let layout = Layout::new().title("<b>Efficient Frontier</b>".into());
-    plot.set_layout(layout);
+    for split in [&max_sharpe_split, &min_variance_split] {
+        volatilities.push(portfolio_statistics.std(split));
+        averages.push(portfolio_statistics.average(split));
+        splits.push(split.clone());
+    }
 
-    let splits_hm = build_splits_hashmap(
-        &statistics.volatilities,
-        &statistics.average_returns,
-        &statistics.splits,
-    );
+    let metric = config.objective.metric;
+
+    // Only the optimizer's splits are simulated net of costs, since doing so for every
+    // split of the grid would be too slow, unless the CVaR constraint excludes all of
+    // them.
+    let mut candidates = frontier.clone();
+    candidates.push(min_variance_split.clone());
+
+    let choose = |candidates: &[Vec<f64>]| {
+        choose_split(
+            &funds,
+            &risk_free,
+            &minimum_acceptable,
+            &config,
+            &max_sharpe_split,
+            candidates,
+        )
+    };
 
-    let html = plot.to_html();
+    let best_split = match choose(&candidates)? {
+        Some(split) => split,
+        None => {
+            let grid = get_possible_splits(funds.len());
+
+            choose(&grid.iterate_over_splits().map(|s| s.to_vec()).collect_vec())?.ok_or(
+                anyhow!(
+                    "No split satisfies the maximum CVaR of {}, searching the candidates of \
+                    the efficient frontier and every split of the grid",
+                    config.risk.max_cvar.unwrap_or_default()
+                ),
+            )?
+        }
+    };
+    let best = build_allocation(
+        &config,
+        &risk_free,
+        &minimum_acceptable,
+        &comparisons,
+        &funds,
+        &dividends,
+        &best_split,
+    )?;
+
+    let efficient_frontier = EfficientFrontier {
+        funds: portfolio_statistics.ids.clone(),
+        points: extract_efficient_frontier(&volatilities, &averages, &splits),
+        grid_validation,
+    };
 
-    let path = Path::new("data/04_visualization/efficient_frontier.html");
-    std::fs::write(path, html)?;
+    // Efficient Frontier
 
-    // let path = Path::new("data/04_visualization/efficient_frontier.png");
-    // plot.write_image(path, plotly::ImageFormat::PNG, 1920, 1080, 1.0);
+    let mut plot = Plot::new();
+
+    if let Some(grid_statistics) = &grid_statistics {
+        let splits_as_text = grid_statistics
+            .splits
+            .iter()
+            .map(|x| hover_text(&portfolio_statistics, x))
+            .collect::<Vec<_>>();
+
+        let scatter = Scatter::new(
+            grid_statistics.volatilities.clone(),
+            grid_statistics.average_returns.clone(),
+        )
+        .mode(Mode::Markers)
+        .name("Grid")
+        .hover_text_array(splits_as_text);
 
-    // Convex Hull
-    let points = statistics
-        .volatilities
+        plot.add_trace(scatter);
+    }
+
+    let (x, y): (Vec<f64>, Vec<f64>) = efficient_frontier
+        .points
         .iter()
-        .zip(&statistics.average_returns)
-        .map(|(x, y)| Point::new(*x, *y))
-        .collect();
-    let x = MultiPoint::new(points);
-    let ch = x.convex_hull();
-    let (x, y): (Vec<f64>, Vec<f64>) = ch.exterior().points().map(|p| p.x_y()).unzip();
-
-    let splits_for_ch = recover_splits(&splits_hm, &x, &y);
-    let splits_as_text_for_ch = splits_for_ch
+        .map(|p| (p.volatility, p.average))
+        .unzip();
+    let frontier_as_text = efficient_frontier
+        .points
         .iter()
-        .map(|x| format!("Splits: {:.2?}", x))
-        .collect();
+        .map(|p| hover_text(&portfolio_statistics, &p.split))
+        .collect::<Vec<_>>();
 
     let scatter = Scatter::new(x, y)
-        .mode(Mode::Markers)
-        .hover_text_array(splits_as_text_for_ch);
-
-    let mut plot = Plot::new();
+        .mode(Mode::LinesMarkers)
+        .name("Efficient frontier")
+        .hover_text_array(frontier_as_text);
     plot.add_trace(scatter);
-    let layout = Layout::new().title("<b>Convex hull</b>".into());
+
+    let best_name = format!("Best {:?} ratio", metric);
+
+    let mut highlighted = vec![
+        (
+            "Max Sharpe ratio",
+            &max_sharpe_allocation,
+            &max_sharpe_split,
+        ),
+        (
+            "Min variance",
+            &min_variance_allocation,
+            &min_variance_split,
+        ),
+    ];
+
+    if best_split != max_sharpe_split {
+        highlighted.push((best_name.as_str(), &best, &best_split));
+    }
+
+    for (name, allocation, split) in highlighted {
+        let scatter = Scatter::new(vec![allocation.volatility], vec![allocation.average])
+            .mode(Mode::Markers)
+            .name(name)
+            .hover_text_array(vec![hover_text(&portfolio_statistics, split)]);
+
+        plot.add_trace(scatter);
+    }
+
+    for benchmark in &benchmark_comparisons {
+        let scatter = Scatter::new(vec![benchmark.volatility], vec![benchmark.average])
+            .mode(Mode::Markers)
+            .name(benchmark.id.to_string());
+
+        plot.add_trace(scatter);
+    }
+
+    let frequency = portfolio_statistics.frequency;
+    let layout = Layout::new()
+        .title("<b>Efficient Frontier</b>".into())
+        .x_axis(Axis::new().title(format!("Volatility ({})", frequency).as_str().into()))
+        .y_axis(Axis::new().title(format!("Average return ({})", frequency).as_str().into()));
     plot.set_layout(layout);
 
     let html = plot.to_html();
 
-    let path = Path::new("data/04_visualization/convex_hull.html");
+    let path = Path::new("data/04_visualization/efficient_frontier.html");
     std::fs::write(path, html)?;
 
-    // let path = Path::new("data/04_visualization/convex_hull.png");
+    // let path = Path::new("data/04_visualization/efficient_frontier.png");
     // plot.write_image(path, plotly::ImageFormat::PNG, 1920, 1080, 1.0);
 
+    let jsonified_frontier = serde_json::to_string(&efficient_frontier)?;
+    let path = Path::new("data/05_output/efficient_frontier.json");
+
+    std::fs::write(path, jsonified_frontier)?;
+
+    let statistics = grid_statistics.as_ref().unwrap_or(&frontier_statistics);
+    let splits_as_text = statistics
+        .splits
+        .iter()
+        .map(|x| hover_text(&portfolio_statistics, x))
+        .collect::<Vec<_>>();
+
     // Returns
     let scatter = Scatter::new(
         statistics.volatilities.clone(),
         statistics.returns_at_end.clone(),
     )
     .mode(Mode::Markers)
+    .name("Allocations")
     .hover_text_array(splits_as_text);
 
     let mut plot = Plot::new();
 
     plot.add_trace(scatter);
-    let layout = Layout::new().title("<b>Risk / Return</b>".into());
+
+    for benchmark in &benchmark_comparisons {
+        let scatter = Scatter::new(
+            vec![benchmark.volatility],
+            vec![benchmark.expected_returns_at_end],
+        )
+        .mode(Mode::Markers)
+        .name(benchmark.id.to_string());
+
+        plot.add_trace(scatter);
+    }
+
+    let layout = Layout::new()
+        .title("<b>Risk / Return</b>".into())
+        .x_axis(Axis::new().title(format!("Volatility ({})", frequency).as_str().into()))
+        .y_axis(Axis::new().title("Value at end".into()));
     plot.set_layout(layout);
 
     let html = plot.to_html();
@@ -280,34 +544,234 @@ pub fn main() -> Result<()> {
     // let path = Path::new("data/04_visualization/risk_return.png");
     // plot.write_image(path, plotly::ImageFormat::PNG, 1920, 1080, 1.0);
 
-    let idx = statistics
-        .sharpe_ratios
+    // Rebalancing
+    let mut plot = Plot::new();
+    let mut rebalancing_comparisons = Vec::new();
+
+    for policy in [
+        RebalancePolicy::ConstantMix,
+        RebalancePolicy::BuyAndHold,
+        RebalancePolicy::Band {
+            threshold: config.simulation.band,
+        },
+    ] {
+        let simulation = simulate(&funds, &best_split, policy, &config.costs)?;
+        let ts = simulation.timeseries(format!("{:?}", policy))?;
+        let gross = simulation.gross_timeseries(format!("{:?} (gross)", policy))?;
+
+        let dates = simulation
+            .dates
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+        let scatter = Scatter::new(dates.clone(), ts.equity_curve(1.0))
+            .mode(Mode::Lines)
+            .name(ts.id.to_string());
+        plot.add_trace(scatter);
+
+        let scatter = Scatter::new(dates, gross.equity_curve(1.0))
+            .mode(Mode::Lines)
+            .line(Line::new().dash(DashType::Dash))
+            .name(gross.id.to_string());
+        plot.add_trace(scatter);
+
+        rebalancing_comparisons.push(RebalancingComparison {
+            simulation,
+            expected_returns_at_end: ts.calculate_value_at_end(1.0),
+            gross_expected_returns_at_end: gross.calculate_value_at_end(1.0),
+            max_drawdown: ts.max_drawdown(),
+        });
+    }
+
+    let dates = portfolio_statistics
+        .dates
         .iter()
-        .enumerate()
-        .max_by(|(_, x), (_, y)| x.partial_cmp(y).expect("No NaNs should exist for ordering"))
-        .expect("At least one sharpe ratio should exist to get the best one.")
-        .0;
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>();
 
-    let best_split = &statistics.splits[idx];
-    let allocations = HashMap::from_iter(
-        funds
-            .iter()
-            .map(|f| f.id.to_string())
-            .zip(best_split.iter().copied()),
-    );
+    for benchmark in &benchmark_comparisons {
+        let scatter = Scatter::new(dates.clone(), benchmark.equity_curve.clone())
+            .mode(Mode::Lines)
+            .line(Line::new().dash(DashType::Dot))
+            .name(benchmark.id.to_string());
+        plot.add_trace(scatter);
+    }
 
-    let allocation = Allocation {
-        allocations,
-        average: statistics.average_returns[idx],
-        expected_returns_at_end: statistics.returns_at_end[idx],
-        sharpe_ratio: statistics.sharpe_ratios[idx],
-        volatility: statistics.volatilities[idx],
+    let layout = Layout::new()
+        .title("<b>Value of the chosen allocation by rebalancing policy</b>".into())
+        .y_axis(Axis::new().title("Value".into()));
+    plot.set_layout(layout);
+
+    let html = plot.to_html();
+
+    let path = Path::new("data/04_visualization/rebalancing.html");
+    std::fs::write(path, html)?;
+
+    // let path = Path::new("data/04_visualization/rebalancing.png");
+    // plot.write_image(path, plotly::ImageFormat::PNG, 1920, 1080, 1.0);
+
+    let rebalancing = RebalancingReport {
+        policies: rebalancing_comparisons,
+        benchmarks: benchmark_comparisons.clone(),
+    };
+
+    let jsonified_rebalancing = serde_json::to_string(&rebalancing)?;
+    let path = Path::new("data/05_output/rebalancing.json");
+
+    std::fs::write(path, jsonified_rebalancing)?;
+
+    // Drawdown
+    let p = Portfolio::new(&funds, &best_split)?;
+    let dates = p.dates().iter().map(|d| d.to_string()).collect::<Vec<_>>();
+
+    let underwater = Scatter::new(dates.clone(), p.underwater())
+        .mode(Mode::Lines)
+        .fill(Fill::ToZeroY)
+        .name("Drawdown");
+    let equity_curve = Scatter::new(dates.clone(), p.equity_curve(1.0))
+        .mode(Mode::Lines)
+        .name("Value")
+        .y_axis("y2");
+
+    let mut plot = Plot::new();
+
+    plot.add_trace(underwater);
+    plot.add_trace(equity_curve);
+
+    for benchmark in &benchmark_comparisons {
+        let scatter = Scatter::new(dates.clone(), benchmark.equity_curve.clone())
+            .mode(Mode::Lines)
+            .line(Line::new().dash(DashType::Dot))
+            .name(benchmark.id.to_string())
+            .y_axis("y2");
+        plot.add_trace(scatter);
+    }
+
+    let layout = Layout::new()
+        .title("<b>Drawdown of the chosen allocation</b>".into())
+        .y_axis(Axis::new().title("Drawdown".into()))
+        .y_axis2(
+            Axis::new()
+                .title("Value".into())
+                .overlaying("y")
+                .side(AxisSide::Right),
+        );
+    plot.set_layout(layout);
+
+    let html = plot.to_html();
+
+    let path = Path::new("data/04_visualization/drawdown.html");
+    std::fs::write(path, html)?;
+
+    // let path = Path::new("data/04_visualization/drawdown.png");
+    // plot.write_image(path, plotly::ImageFormat::PNG, 1920, 1080, 1.0);
+
+    // Cash flows
+    let cash_flows = CashFlowsReport {
+        portfolio: p.project_cash_flows(&config.cash_flows)?,
+        benchmarks: comparisons
+            .iter()
+            .map(|b| {
+                Ok(BenchmarkProjection {
+                    id: b.id.clone(),
+                    projection: at_dates(b, p.dates())?.project_cash_flows(&config.cash_flows)?,
+                })
+            })
+            .collect::<Result<_>>()?,
     };
 
-    let jsonified_allocation = serde_json::to_string(&allocation)?;
+    let jsonified_projection = serde_json::to_string(&cash_flows)?;
+    let path = Path::new("data/05_output/cash_flows.json");
+
+    std::fs::write(path, jsonified_projection)?;
+
+    // Monte Carlo
+    let settings = &config.monte_carlo;
+    let paths = sample_paths(&funds, &comparisons, &best_split, settings)?;
+
+    let mut date = *p.dates().last().expect("Portfolio should have periods");
+    let future_dates = (0..settings.horizon)
+        .map(|_| {
+            date = frequency.next_period(date);
+            date
+        })
+        .collect::<Vec<_>>();
+
+    let monte_carlo = monte_carlo_report(&paths, settings, future_dates)?;
+    let dates = monte_carlo
+        .dates
+        .iter()
+        .map(|d| d.to_string())
+        .collect::<Vec<_>>();
+
+    let mut plot = Plot::new();
+
+    for (i, percentile) in monte_carlo.percentiles.iter().enumerate() {
+        let mut scatter = Scatter::new(dates.clone(), percentile.values.clone())
+            .mode(Mode::Lines)
+            .name(format!("Percentile {:.0}", 100.0 * percentile.percentile));
+
+        if i > 0 {
+            scatter = scatter.fill(Fill::ToNextY);
+        }
+
+        plot.add_trace(scatter);
+    }
+
+    let mut probabilities = Vec::new();
+
+    for benchmark in &monte_carlo.benchmarks {
+        let scatter = Scatter::new(dates.clone(), benchmark.median.clone())
+            .mode(Mode::Lines)
+            .line(Line::new().dash(DashType::Dash))
+            .name(format!("{} (median)", benchmark.id));
+        plot.add_trace(scatter);
+
+        probabilities.push(format!(
+            "P(beat {}) = {:.1}%",
+            benchmark.id,
+            100.0 * benchmark.probability_of_beating
+        ));
+    }
+    probabilities.push(format!(
+        "P(loss) = {:.1}%",
+        100.0 * monte_carlo.probability_of_loss
+    ));
+
+    let layout = Layout::new()
+        .title(
+            format!(
+                "<b>Simulated value of the chosen allocation</b><br>{}",
+                probabilities.join(", ")
+            )
+            .as_str()
+            .into(),
+        )
+        .y_axis(Axis::new().title("Value".into()));
+    plot.set_layout(layout);
+
+    let html = plot.to_html();
+
+    let path = Path::new("data/04_visualization/monte_carlo.html");
+    std::fs::write(path, html)?;
+
+    // let path = Path::new("data/04_visualization/monte_carlo.png");
+    // plot.write_image(path, plotly::ImageFormat::PNG, 1920, 1080, 1.0);
+
+    let jsonified_monte_carlo = serde_json::to_string(&monte_carlo)?;
+    let path = Path::new("data/05_output/monte_carlo.json");
+
+    std::fs::write(path, jsonified_monte_carlo)?;
+
+    let jsonified_allocation = serde_json::to_string(&best)?;
     let path = Path::new("data/05_output/allocation.json");
 
     std::fs::write(path, jsonified_allocation)?;
 
+    let jsonified_allocation = serde_json::to_string(&min_variance_allocation)?;
+    let path = Path::new("data/05_output/min_variance_allocation.json");
+
+    std::fs::write(path, jsonified_allocation)?;
+
     Ok(())
 }
@@ -0,0 +1,180 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+use investments::{
+    benchmark::at_dates,
+    config::{get_config, MonteCarlo},
+    frontier::EfficientFrontier,
+    id::SeriesId,
+    montecarlo::{sample_paths, BenchmarkPaths, Paths},
+    planning::{historical_paths, plan, Distribution, Plan},
+    portfolio::{align, AllTimeSeries, Portfolio, TimeSeries},
+};
+
+#[derive(Deserialize)]
+struct Allocation {
+    allocations: HashMap<SeriesId, f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BenchmarkPlan {
+    id: SeriesId,
+    #[serde(flatten)]
+    plan: Plan,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PlanningReport {
+    funds: Vec<SeriesId>,
+    target: f64,
+    horizon: usize,
+    confidence: f64,
+    initial: f64,
+    /// Distribution the paths were drawn from, which is the simulated one when the
+    /// horizon is longer than the history.
+    distribution: Distribution,
+    chosen_allocation: Plan,
+    /// Allocation of the efficient frontier requiring the smallest contribution.
+    best_on_frontier: Option<Plan>,
+    /// Plans investing entirely in each comparison benchmark instead.
+    benchmarks: Vec<BenchmarkPlan>,
+}
+
+fn load_frontier() -> Result<EfficientFrontier> {
+    let path = Path::new("data/05_output/efficient_frontier.json");
+    let frontier = std::fs::read_to_string(path)?;
+
+    Ok(serde_json::from_str(&frontier)?)
+}
+
+fn load_allocation() -> Result<Allocation> {
+    let path = Path::new("data/05_output/allocation.json");
+    let allocation = std::fs::read_to_string(path)?;
+
+    Ok(serde_json::from_str(&allocation)?)
+}
+
+pub fn main() -> Result<()> {
+    let config = get_config();
+    let settings = &config.planning;
+
+    let frontier = load_frontier()?;
+    let allocation = load_allocation()?;
+    let comparisons = config.benchmarks.comparisons(
+        &AllTimeSeries::load(Path::new("data/03_timeseries/benchmarks.json"))?.timeseries,
+    )?;
+
+    let all_funds = AllTimeSeries::load(Path::new("data/03_timeseries/models.json"))?.timeseries;
+    let funds = frontier
+        .funds
+        .iter()
+        .map(|id| {
+            all_funds
+                .iter()
+                .find(|f| &f.id == id)
+                .cloned()
+                .ok_or_else(|| anyhow!("Fund '{}' of the frontier has no time series", id))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let periods = align(&funds)?.len();
+    let distribution = match settings.distribution {
+        Distribution::Historical if settings.horizon > periods => Distribution::Simulated,
+        distribution => distribution,
+    };
+
+    let chosen_split = frontier
+        .funds
+        .iter()
+        .map(|id| allocation.allocations.get(id).copied().unwrap_or(0.0))
+        .collect::<Vec<_>>();
+
+    let monte_carlo = MonteCarlo {
+        horizon: settings.horizon,
+        percentiles: Vec::new(),
+        ..config.monte_carlo
+    };
+
+    let paths_for = |split: &[f64], benchmarks: &[TimeSeries]| -> Result<Paths> {
+        match distribution {
+            Distribution::Historical => {
+                let p = Portfolio::new(&funds, split)?;
+
+                Ok(Paths {
+                    portfolio: historical_paths(p.returns(), settings.horizon),
+                    benchmarks: benchmarks
+                        .iter()
+                        .map(|b| {
+                            Ok(BenchmarkPaths {
+                                id: b.id.clone(),
+                                paths: historical_paths(
+                                    &at_dates(b, p.dates())?.returns,
+                                    settings.horizon,
+                                ),
+                            })
+                        })
+                        .collect::<Result<_>>()?,
+                })
+            }
+            Distribution::Simulated => sample_paths(&funds, benchmarks, split, &monte_carlo),
+        }
+    };
+
+    let plan_for = |paths: &[Vec<f64>], split: Vec<f64>| {
+        plan(
+            paths,
+            split,
+            settings.initial,
+            settings.target,
+            settings.confidence,
+        )
+    };
+
+    let paths = paths_for(&chosen_split, &comparisons)?;
+    let chosen_allocation = plan_for(&paths.portfolio, chosen_split);
+    let benchmarks = paths
+        .benchmarks
+        .iter()
+        .map(|b| BenchmarkPlan {
+            id: b.id.clone(),
+            plan: plan_for(&b.paths, vec![1.0]),
+        })
+        .collect();
+
+    let best_on_frontier = frontier
+        .points
+        .iter()
+        .map(|p| {
+            Ok(plan_for(
+                &paths_for(&p.split, &[])?.portfolio,
+                p.split.clone(),
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .min_by(|a, b| {
+            a.required_contribution
+                .partial_cmp(&b.required_contribution)
+                .expect("No NaNs should exist for ordering")
+        });
+
+    let report = PlanningReport {
+        funds: frontier.funds.clone(),
+        target: settings.target,
+        horizon: settings.horizon,
+        confidence: settings.confidence,
+        initial: settings.initial,
+        distribution,
+        chosen_allocation,
+        best_on_frontier,
+        benchmarks,
+    };
+
+    let jsonified_report = serde_json::to_string(&report)?;
+    let path = Path::new("data/05_output/planning.json");
+
+    std::fs::write(path, jsonified_report)?;
+
+    Ok(())
+}
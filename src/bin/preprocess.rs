@@ -2,205 +2,134 @@ use polars::prelude::*;
 use std::path::Path;
 
 use anyhow::{anyhow, Result};
+use investments::{
+    config::{get_config, Config},
+    fii::write_reports,
+    source::{check_overlaps, Format, Series, SourceRecords},
+    validation::validate_broker_files,
+};
 
 pub fn main() -> Result<()> {
-    process_funds()?;
-    process_cdi()?;
+    let config = get_config();
 
-    Ok(())
-}
-
-pub fn get_month(s: &str) -> Result<&str> {
-    match s {
-        "Jan" => Ok("01"),
-        "Fev" => Ok("02"),
-        "Mar" => Ok("03"),
-        "Abr" => Ok("04"),
-        "Mai" => Ok("05"),
-        "Jun" => Ok("06"),
-        "Jul" => Ok("07"),
-        "Ago" => Ok("08"),
-        "Set" => Ok("09"),
-        "Out" => Ok("10"),
-        "Nov" => Ok("11"),
-        "Dez" => Ok("12"),
-        _ => Err(anyhow!("Can't parse this month")),
-    }
-}
-
-pub fn process_cdi() -> Result<()> {
-    let cdi_path = Path::new("data/01_raw/cdi.csv");
-
-    let mut df = CsvReader::from_path(cdi_path)?.finish()?;
-
-    _ = df.drop_in_place("Acumulado")?;
-
-    let vals: Vec<String> = Vec::new();
-    let mut df = df.melt(["Ano/Mês"], vals)?;
-
-    df.rename("variable", "month")?;
-    df.rename("value", "values")?;
-
-    df.apply("values", |x| {
-        x.str()
-            .expect("Column 'values' should be strings with commas instead of dots")
-            .into_iter()
-            .map(|x| {
-                let x = x.expect("Column 'values' should not have missing values");
-                x.replace(',', ".")
-            })
-            .collect::<StringChunked>()
-            .into_series()
-            .to_float()
-            .expect("Should be able to convert 'values' to floats after replacing ',' with '.'")
-    })?;
-
-    df.apply("values", |x| {
-        x.f64()
-            .expect("Column 'values' should be floats after converison")
-            .apply(|x| x.map(|y| y / 100.0))
-    })?;
-
-    let cast_year = df["Ano/Mês"].cast(&DataType::String)?;
-    let year = cast_year
-        .str()
-        .expect("Column 'Ano/Mês' should be strings after data cast.");
-    let n = year.len();
-
-    let month = df["month"]
-        .str()
-        .expect("Column 'month' should be strings representing months, e.g. 'Jan', 'Fev' etc.")
-        .apply(|x| {
-            Some(std::borrow::Cow::from(
-                "-".to_string()
-                    + get_month(x.expect("Column 'month' should not have missing values"))
-                        .expect("Column 'month' should be a month such as 'Jan', 'Fev' etc."),
-            ))
-        });
-
-    let days: Series = std::iter::repeat("-01").take(n).collect();
-    let days = days.str().expect("Series 'days' should consist of strings");
-
-    let dt = year.clone() + month.clone() + days.clone();
-    let dt = dt.with_name("dt");
-
-    df.with_column(dt)?;
-    _ = df.drop_in_place("Ano/Mês")?;
-    _ = df.drop_in_place("month")?;
-
-    df.sort_in_place(["dt"], vec![false], true)?;
-
-    let path = Path::new("data/02_preprocessed/cdi.csv");
-    let file = std::fs::File::create(path)?;
-
-    CsvWriter::new(file).finish(&mut df)?;
+    validate(&config)?;
 
-    Ok(())
-}
+    let mut funds = Vec::new();
+    let mut benchmarks = Vec::new();
+    let mut fii_reports = Vec::new();
+    let mut skipped = Vec::new();
 
-pub fn process_funds() -> Result<()> {
-    let raw_path = Path::new("data/01_raw");
-    let preprocessed_path = Path::new("data/02_preprocessed");
+    for source in &config.preprocess.sources {
+        let files = source.files()?;
 
-    let mut dataframes = Vec::new();
+        if files.is_empty() {
+            continue;
+        }
 
-    for file in raw_path.join("fundos").read_dir()? {
-        let csv_file = file?;
+        let reader = source.format.source();
+        let data = reader.read(&files)?;
+        fii_reports.extend(data.fii_reports);
+        skipped.extend(data.skipped);
 
-        let mut df = CsvReader::from_path(csv_file.path())?
-            .has_header(true)
-            .finish()?;
+        let records = SourceRecords {
+            path: source.path.clone(),
+            frequency: reader.frequency(),
+            records: data.records,
+        };
 
-        let path = csv_file.path();
-        let name = path.file_stem().ok_or(anyhow!("File name not found"))?;
-        let file_name = name.to_str().ok_or(anyhow!("Invalid UTF8 for file name"))?;
+        match source.series {
+            Series::Funds => funds.push(records),
+            Series::Benchmarks => benchmarks.push(records),
+        }
+    }
 
-        let file_name = file_name.replacen('_', "/", 1);
-        let (cnpj, year) = file_name
-            .split_once('_')
-            .ok_or(anyhow!("Invalid file name. Couldn't split CNPJ and date."))?;
+    let path = Path::new("data/06_logs/skipped_rows.json");
+    std::fs::write(path, serde_json::to_string(&skipped)?)?;
 
-        _ = df.drop_in_place("");
-        _ = df.drop_in_place("Acumulado");
+    process_funds(funds)?;
+    process_benchmarks(benchmarks)?;
 
-        let mut transposed = df
-            .transpose(Some("month"), None)
-            .expect("Could not transpose");
+    if !fii_reports.is_empty() {
+        write_reports(fii_reports, Path::new("data/02_preprocessed/fii.csv"))?;
+    }
 
-        transposed
-            .rename("column_0", "values")
-            .expect("Could not rename");
+    Ok(())
+}
 
-        let months = transposed["month"].clone();
-        let s = months.str().expect("Months should be strings");
+/// Validates the files of every broker source together before reading any of them, so
+/// that the same fund and year in two sources is caught, writing every issue found to
+/// `validation.json` and failing on errors.
+fn validate(config: &Config) -> Result<()> {
+    let mut files = Vec::new();
 
-        let months_as_numbers = s.apply(|x| {
-            let month = get_month(x.expect("Months should be strings"))
-                .expect("Months should be 3-letter and in Portuguese");
+    for source in &config.preprocess.sources {
+        if source.format == Format::Broker {
+            files.extend(source.files()?);
+        }
+    }
 
-            Some(std::borrow::Cow::from(month))
-        });
+    let today = chrono::Local::now().date_naive();
+    let report = validate_broker_files(&files, &config.validation, today)?;
 
-        let len = months_as_numbers.len();
+    let path = Path::new("data/06_logs/validation.json");
+    std::fs::write(path, serde_json::to_string(&report)?)?;
 
-        let year: Series = std::iter::repeat(year.to_string() + "-")
-            .take(len)
-            .collect();
-        let year = year.str()?;
+    report.check()
+}
 
-        let day: Series = std::iter::repeat("-01").take(len).collect();
-        let day = day.str()?;
+/// Writes the records of all `sources` sorted by id and date, along with the frequency
+/// of their returns, failing if the sources overlap.
+fn write_records(sources: Vec<SourceRecords>, id_column: &str, path: &Path) -> Result<()> {
+    check_overlaps(&sources)?;
 
-        let dt = year.clone() + months_as_numbers + day.clone();
-        let dt = Series::from(dt);
-        let dt = dt.with_name("dt");
+    let mut records = sources
+        .into_iter()
+        .flat_map(|s| {
+            let frequency = s.frequency;
+            s.records.into_iter().map(move |r| (r, frequency))
+        })
+        .collect::<Vec<_>>();
 
-        transposed.with_column(dt)?;
-        _ = transposed.drop_in_place("month")?;
+    records.sort_by(|(a, _), (b, _)| (&a.id, a.date).cmp(&(&b.id, b.date)));
 
-        let cnpj: Series = std::iter::repeat(cnpj).take(len).collect();
-        let cnpj = cnpj.with_name("CNPJ_Fundo");
+    let mut df = df!(
+        "values" => records.iter().map(|(r, _)| r.value).collect::<Vec<_>>(),
+        "dt" => records.iter().map(|(r, _)| r.date.to_string()).collect::<Vec<_>>(),
+        id_column => records.iter().map(|(r, _)| r.id.to_string()).collect::<Vec<_>>(),
+        "frequency" => records.iter().map(|(_, f)| f.to_string()).collect::<Vec<_>>(),
+    )?;
 
-        transposed.with_column(cnpj)?;
+    let file = std::fs::File::create(path)?;
 
-        transposed.apply("values", |series| {
-            series
-                .str()
-                .expect("'values' column was not a string.")
-                .into_iter()
-                .map(|value| value.map(|x| x.replace(',', ".")))
-                .collect::<StringChunked>()
-                .into_series()
-                .to_float()
-                .expect("Could not convert to float")
-        })?;
+    CsvWriter::new(file).finish(&mut df)?;
 
-        transposed.apply("values", |series| {
-            series
-                .f64()
-                .expect("'values' should be floats by now.")
-                .apply(|entry| entry.map(|x| x / 100.0))
-                .into_series()
-        })?;
+    Ok(())
+}
 
-        dataframes.push(transposed);
+pub fn process_benchmarks(sources: Vec<SourceRecords>) -> Result<()> {
+    if sources.iter().all(|s| s.records.is_empty()) {
+        return Err(anyhow!(
+            "No benchmark records found in the sources of the config"
+        ));
     }
 
-    let mut df = dataframes
-        .into_iter()
-        .reduce(|acc, df| {
-            acc.vstack(&df)
-                .expect("Should be able to vertically stack dataframes")
-        })
-        .expect("Should have more than one dataframe");
-
-    df.sort_in_place(["CNPJ_Fundo", "dt"], vec![false, false], true)?;
-
-    let path = Path::new(preprocessed_path);
-
-    let file = std::fs::File::create(path.join("funds.csv"))?;
+    write_records(
+        sources,
+        "id",
+        Path::new("data/02_preprocessed/benchmarks.csv"),
+    )
+}
 
-    CsvWriter::new(file).finish(&mut df)?;
+pub fn process_funds(sources: Vec<SourceRecords>) -> Result<()> {
+    if sources.iter().all(|s| s.records.is_empty()) {
+        return Err(anyhow!(
+            "No fund records found in the sources of the config"
+        ));
+    }
 
-    Ok(())
+    write_records(
+        sources,
+        "CNPJ_Fundo",
+        Path::new("data/02_preprocessed/funds.csv"),
+    )
 }
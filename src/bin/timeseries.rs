@@ -1,4 +1,5 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
 use std::path::Path;
 
 use polars::{
@@ -6,8 +7,13 @@ use polars::{
     prelude::*,
 };
 
+use investments::benchmark::poupanca;
+use investments::calendar::Calendar;
+use investments::cnpj::Cnpj;
 use investments::config::get_config;
-use investments::portfolio::{AllTimeSeries, TimeSeries};
+use investments::id::SeriesId;
+use investments::missing::{handle_missing, Observations};
+use investments::portfolio::{AllTimeSeries, Frequency, TimeSeries};
 
 pub fn load_all_funds() -> Result<DataFrame> {
     let path = Path::new("data/02_preprocessed/funds.csv");
@@ -15,34 +21,70 @@ pub fn load_all_funds() -> Result<DataFrame> {
     Ok(CsvReader::from_path(path)?.has_header(true).finish()?)
 }
 
-pub fn load_cdi() -> Result<DataFrame> {
-    let path = Path::new("data/02_preprocessed/cdi.csv");
+pub fn load_benchmarks() -> Result<DataFrame> {
+    let path = Path::new("data/02_preprocessed/benchmarks.csv");
 
     Ok(CsvReader::from_path(path)?.has_header(true).finish()?)
 }
 
-pub fn convert_funds_into_timeseries(
+fn parse_dates(df: &DataFrame) -> Result<Vec<NaiveDate>> {
+    df["dt"]
+        .str()
+        .expect("Column 'dt' should be strings in the format YYYY-MM-DD")
+        .into_iter()
+        .map(|x| {
+            let x = x.expect("Date should be present in all rows for column 'dt'");
+            Ok(NaiveDate::parse_from_str(x, "%Y-%m-%d")?)
+        })
+        .collect()
+}
+
+/// Frequency of the returns in `df`, which are monthly unless stated otherwise.
+fn parse_frequency(df: &DataFrame) -> Result<Frequency> {
+    if !df.get_column_names().contains(&"frequency") {
+        return Ok(Frequency::Monthly);
+    }
+
+    match df["frequency"].str()?.get(0) {
+        Some(frequency) => frequency.parse(),
+        None => Ok(Frequency::Monthly),
+    }
+}
+
+/// Returns of each distinct value of `id_column`, identified by `parse_id`, at their own
+/// frequency, dated from `from_date` up to the end of the period of `frequency` starting
+/// at `to_date`.
+fn load_observations(
     df: DataFrame,
+    id_column: &str,
+    parse_id: impl Fn(&str) -> Result<SeriesId>,
     from_date: &str,
     to_date: &str,
-) -> Vec<TimeSeries> {
-    let names = df["CNPJ_Fundo"]
+    frequency: Frequency,
+) -> Result<Vec<Observations>> {
+    // Finer returns of the last period may be dated up to its end.
+    let end = frequency
+        .next_period(NaiveDate::parse_from_str(to_date, "%Y-%m-%d")?)
+        .to_string();
+
+    let names = df[id_column]
         .unique()
-        .expect("Column 'CNPJ_Fundo' should be present");
+        .unwrap_or_else(|_| panic!("Column '{}' should be present", id_column));
 
-    let cnpjs = names.str().expect("Column 'CNPJ_Fundo' should be strings");
+    let ids = names
+        .str()
+        .unwrap_or_else(|_| panic!("Column '{}' should be strings", id_column));
 
     let lazy = df.lazy();
 
-    cnpjs
-        .into_iter()
+    ids.into_iter()
         .map(|name| {
-            let name = name.expect("CNPJ has to be present for all rows");
+            let name = name.expect("Id has to be present for all rows");
             let df = lazy
                 .clone()
-                .filter(col("CNPJ_Fundo").eq(lit(name)))
+                .filter(col(id_column).eq(lit(name)))
                 .filter(col("dt").gt_eq(lit(from_date)))
-                .filter(col("dt").lt_eq(lit(to_date)));
+                .filter(col("dt").lt(lit(end.as_str())));
 
             let df = df.collect().expect("Filtering should be possible");
 
@@ -50,59 +92,104 @@ pub fn convert_funds_into_timeseries(
                 .f64()
                 .expect("Values should be floats")
                 .into_iter()
-                .map(|x| x.expect("Value should be present in all rows for column 'values'"))
                 .collect();
 
-            TimeSeries::new(name.to_string(), values)
+            Ok(Observations {
+                id: parse_id(name)?,
+                dates: parse_dates(&df)?,
+                values,
+                frequency: parse_frequency(&df)?,
+            })
         })
         .collect()
 }
 
-pub fn convert_cdi_into_timeseries(df: DataFrame, from_date: &str, to_date: &str) -> TimeSeries {
-    let lazy = df.lazy();
+/// Adds the savings account (poupança) computed from the Selic when there is one but
+/// not the other.
+pub fn with_poupanca(mut benchmarks: Vec<TimeSeries>) -> Result<Vec<TimeSeries>> {
+    let selic = benchmarks.iter().find(|b| b.id == "selic");
+    if let (Some(selic), false) = (selic, benchmarks.iter().any(|b| b.id == "poupanca")) {
+        benchmarks.push(poupanca(selic)?);
+    }
 
-    let df = lazy
-        .clone()
-        .filter(col("dt").gt_eq(lit(from_date)))
-        .filter(col("dt").lt_eq(lit(to_date)));
+    benchmarks.sort_by(|a, b| a.id.cmp(&b.id));
 
-    let df = df.collect().expect("Filtering should be possible");
+    Ok(benchmarks)
+}
 
-    let values = df["values"]
-        .f64()
-        .expect("Value should be floats")
-        .into_iter()
-        .map(|x| x.expect("Value should be present in all rows for column 'values'"))
-        .collect();
+fn write_json<T: serde::Serialize>(value: &T, path: &str) -> Result<()> {
+    std::fs::write(Path::new(path), serde_json::to_string(value)?)?;
 
-    TimeSeries::new("_cdi".to_string(), values)
+    Ok(())
 }
 
 pub fn main() -> Result<()> {
     let config = get_config();
-    let funds = load_all_funds()?;
-
-    let timeseries = convert_funds_into_timeseries(
-        funds,
-        &config.portfolio.from_date,
-        &config.portfolio.to_date,
-    );
-
-    let all_timeseries = AllTimeSeries { timeseries };
-
-    let jsonified_ts = serde_json::to_string(&all_timeseries)?;
-    let path = Path::new("data/03_timeseries/models.json");
-
-    std::fs::write(path, jsonified_ts)?;
-
-    let cdi = load_cdi()?;
-    let cdi_ts =
-        convert_cdi_into_timeseries(cdi, &config.portfolio.from_date, &config.portfolio.to_date);
-
-    let jsonified_ts = serde_json::to_string(&cdi_ts)?;
-    let path = Path::new("data/03_timeseries/cdi.json");
+    let portfolio = &config.portfolio;
+    let (from, to) = portfolio.window()?;
+
+    let funds = load_observations(
+        load_all_funds()?,
+        "CNPJ_Fundo",
+        |name| {
+            let cnpj: Cnpj = name
+                .parse()
+                .map_err(|e| anyhow!("Invalid fund in 'funds.csv': {}", e))?;
+
+            Ok(SeriesId::Fund(cnpj))
+        },
+        &portfolio.from_date,
+        &portfolio.to_date,
+        portfolio.frequency,
+    )?;
+    let benchmarks = load_observations(
+        load_benchmarks()?,
+        "id",
+        |name| Ok(SeriesId::Named(name.to_string())),
+        &portfolio.from_date,
+        &portfolio.to_date,
+        portfolio.frequency,
+    )?;
+    let benchmark_ids: Vec<SeriesId> = benchmarks.iter().map(|b| b.id.clone()).collect();
+
+    // The savings account is computed from the Selic when it has no series of its own.
+    let required: Vec<String> = std::iter::once(&config.benchmarks.risk_free)
+        .chain(&config.benchmarks.comparisons)
+        .map(|id| match id.as_str() {
+            "poupanca" if !benchmark_ids.iter().any(|b| b == id) => "selic".to_string(),
+            _ => id.clone(),
+        })
+        .collect();
 
-    std::fs::write(path, jsonified_ts)?;
+    // Funds and benchmarks are handled together, so that the periods dropped from one
+    // are also dropped from the other.
+    let (all, report) = handle_missing(
+        &[funds, benchmarks].concat(),
+        &config.missing_data,
+        portfolio.frequency,
+        &Calendar::anbima(),
+        &required,
+    )?;
+    write_json(&report, "data/06_logs/missing_data.json")?;
+
+    let all = all
+        .iter()
+        .map(|ts| ts.slice(from, to))
+        .collect::<Result<Vec<_>>>()?;
+    let (benchmarks, timeseries): (Vec<_>, Vec<_>) = all
+        .into_iter()
+        .partition(|ts| benchmark_ids.contains(&ts.id));
+
+    write_json(
+        &AllTimeSeries { timeseries },
+        "data/03_timeseries/models.json",
+    )?;
+
+    let timeseries = with_poupanca(benchmarks)?;
+    write_json(
+        &AllTimeSeries { timeseries },
+        "data/03_timeseries/benchmarks.json",
+    )?;
 
     Ok(())
 }
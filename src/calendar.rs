@@ -0,0 +1,236 @@
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+/// Business days in a year by the ANBIMA convention, used for annualizing daily rates.
+pub const BUSINESS_DAYS_PER_YEAR: f64 = 252.0;
+
+/// Days on which the market is open: weekdays that are not holidays.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Calendar {
+    /// Whether the national holidays of [`national_holidays`] are closed, for any year.
+    national: bool,
+    holidays: BTreeSet<NaiveDate>,
+}
+
+/// Easter Sunday of `year` in the Gregorian calendar, by the anonymous algorithm
+/// (Meeus/Jones/Butcher).
+pub fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).expect("Easter is a valid date")
+}
+
+/// National holidays of `year` on which ANBIMA and B3 are closed: the fixed ones, the
+/// Black Consciousness Day since 2024, and Carnival, Good Friday and Corpus Christi,
+/// which move with Easter.
+pub fn national_holidays(year: i32) -> Vec<NaiveDate> {
+    let fixed = |month, day| NaiveDate::from_ymd_opt(year, month, day).expect("Dates are valid");
+    let easter = easter(year);
+
+    let mut holidays = vec![
+        fixed(1, 1),
+        easter - Days::new(48),
+        easter - Days::new(47),
+        easter - Days::new(2),
+        fixed(4, 21),
+        fixed(5, 1),
+        easter + Days::new(60),
+        fixed(9, 7),
+        fixed(10, 12),
+        fixed(11, 2),
+        fixed(11, 15),
+        fixed(12, 25),
+    ];
+
+    if year >= 2024 {
+        holidays.push(fixed(11, 20));
+    }
+
+    holidays.sort();
+    holidays
+}
+
+impl Calendar {
+    /// Calendar with weekends as the only days without business.
+    pub fn weekdays() -> Calendar {
+        Calendar::default()
+    }
+
+    /// Calendar of ANBIMA, closed on weekends and on the national holidays of any year.
+    pub fn anbima() -> Calendar {
+        Calendar {
+            national: true,
+            holidays: BTreeSet::new(),
+        }
+    }
+
+    pub fn with_holidays(holidays: impl IntoIterator<Item = NaiveDate>) -> Calendar {
+        Calendar {
+            national: false,
+            holidays: holidays.into_iter().collect(),
+        }
+    }
+
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.holidays.contains(&date)
+            || (self.national && national_holidays(date.year()).contains(&date))
+    }
+
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.is_holiday(date)
+    }
+
+    /// First business day on or after `date`.
+    pub fn following(&self, date: NaiveDate) -> NaiveDate {
+        let mut date = date;
+        while !self.is_business_day(date) {
+            date = date + Days::new(1);
+        }
+        date
+    }
+
+    /// Last business day on or before `date`.
+    pub fn preceding(&self, date: NaiveDate) -> NaiveDate {
+        let mut date = date;
+        while !self.is_business_day(date) {
+            date = date - Days::new(1);
+        }
+        date
+    }
+
+    /// Business days from `from`, inclusive, to `to`, exclusive, which is the number of
+    /// daily rates accrued between them. Negative when `to` comes before `from`.
+    pub fn business_days_between(&self, from: NaiveDate, to: NaiveDate) -> i64 {
+        let count = |from: NaiveDate, to: NaiveDate| {
+            from.iter_days()
+                .take_while(|d| *d < to)
+                .filter(|d| self.is_business_day(*d))
+                .count() as i64
+        };
+
+        if from <= to {
+            count(from, to)
+        } else {
+            -count(to, from)
+        }
+    }
+
+    /// Business day `n` business days after `date`, or before it when `n` is negative.
+    /// A `date` that is not a business day is first rolled in the same direction.
+    pub fn add_business_days(&self, date: NaiveDate, n: i64) -> NaiveDate {
+        if n >= 0 {
+            (0..n).fold(self.following(date), |d, _| {
+                self.following(d + Days::new(1))
+            })
+        } else {
+            (0..-n).fold(self.preceding(date), |d, _| {
+                self.preceding(d - Days::new(1))
+            })
+        }
+    }
+
+    pub fn business_days_in_year(&self, year: i32) -> i64 {
+        let first = |year| NaiveDate::from_ymd_opt(year, 1, 1).expect("Dates are valid");
+
+        self.business_days_between(first(year), first(year + 1))
+    }
+
+    /// Last business day of the month of `date`.
+    pub fn last_business_day_of_month(&self, date: NaiveDate) -> NaiveDate {
+        let next_month = if date.month() == 12 {
+            NaiveDate::from_ymd_opt(date.year() + 1, 1, 1)
+        } else {
+            NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1)
+        }
+        .expect("Dates are valid");
+
+        self.preceding(next_month - Days::new(1))
+    }
+}
+
+/// Daily factor of a yearly rate, such as the one the CDI is published as.
+pub fn daily_factor(annual_rate: f64) -> f64 {
+    (1.0 + annual_rate).powf(1.0 / BUSINESS_DAYS_PER_YEAR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn rolls_over_weekends_and_holidays() {
+        let calendar = Calendar::with_holidays([date(2023, 5, 1)]);
+
+        // 2023-04-29 is a Saturday and 2023-05-01 a holiday.
+        assert_eq!(calendar.following(date(2023, 4, 29)), date(2023, 5, 2));
+        assert_eq!(calendar.preceding(date(2023, 5, 1)), date(2023, 4, 28));
+        assert_eq!(calendar.following(date(2023, 5, 3)), date(2023, 5, 3));
+        assert!(Calendar::weekdays().is_business_day(date(2023, 5, 1)));
+    }
+
+    #[test]
+    fn moveable_feasts_follow_easter() {
+        assert_eq!(easter(2023), date(2023, 4, 9));
+        assert_eq!(easter(2024), date(2024, 3, 31));
+        assert_eq!(easter(2025), date(2025, 4, 20));
+
+        let holidays = national_holidays(2024);
+        for holiday in [
+            date(2024, 2, 12),
+            date(2024, 2, 13),
+            date(2024, 3, 29),
+            date(2024, 5, 30),
+            date(2024, 11, 20),
+        ] {
+            assert!(
+                holidays.contains(&holiday),
+                "{} should be a holiday",
+                holiday
+            );
+        }
+        assert!(!national_holidays(2023).contains(&date(2023, 11, 20)));
+    }
+
+    #[test]
+    fn counts_and_adds_business_days() {
+        let calendar = Calendar::anbima();
+
+        // Carnival of 2024 was on Monday 12 and Tuesday 13 of February.
+        let (friday, wednesday) = (date(2024, 2, 9), date(2024, 2, 14));
+        assert_eq!(calendar.business_days_between(friday, wednesday), 1);
+        assert_eq!(calendar.business_days_between(wednesday, friday), -1);
+        assert_eq!(calendar.add_business_days(friday, 1), wednesday);
+        assert_eq!(calendar.add_business_days(wednesday, -1), friday);
+        assert_eq!(calendar.add_business_days(date(2024, 2, 10), 0), wednesday);
+
+        assert_eq!(calendar.business_days_in_year(2023), 249);
+        assert_eq!(
+            calendar.last_business_day_of_month(date(2024, 3, 1)),
+            date(2024, 3, 28)
+        );
+    }
+
+    #[test]
+    fn daily_factor_compounds_to_the_yearly_rate() {
+        assert!((daily_factor(0.1375).powi(252) - 1.1375).abs() < 1e-12);
+        assert_eq!(daily_factor(0.0), 1.0);
+    }
+}
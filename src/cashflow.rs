@@ -0,0 +1,216 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::portfolio::{annualize_growth, TimeSeries};
+
+/// Deposit, or withdrawal when negative, made at the start of the period containing `date`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CashFlow {
+    pub date: NaiveDate,
+    pub amount: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CashFlows {
+    /// Invested at the start of the first period.
+    pub initial: f64,
+    /// Invested at the start of every period after the first one.
+    #[serde(default)]
+    pub recurring: f64,
+    #[serde(default)]
+    pub one_offs: Vec<CashFlow>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Projection {
+    pub dates: Vec<NaiveDate>,
+    /// Net amount invested at the start of each period.
+    pub flows: Vec<f64>,
+    /// Value at the end of each period.
+    pub values: Vec<f64>,
+    pub value_at_end: f64,
+    pub total_invested: f64,
+    /// Internal rate of return per period, if there is one.
+    pub money_weighted_return: Option<f64>,
+    pub annualized_money_weighted_return: Option<f64>,
+    /// Compounded return of the periods with money invested, regardless of the flows.
+    pub time_weighted_return: f64,
+    pub annualized_time_weighted_return: f64,
+}
+
+impl CashFlows {
+    /// Net amount invested at the start of each period of `ts`.
+    pub fn flows(&self, ts: &TimeSeries) -> Result<Vec<f64>> {
+        let mut flows = (0..ts.dates.len())
+            .map(|i| if i == 0 { self.initial } else { self.recurring })
+            .collect::<Vec<_>>();
+
+        let end = ts.dates.last().map(|d| ts.frequency.next_period(*d));
+
+        for flow in &self.one_offs {
+            let period = ts.dates.iter().rposition(|d| *d <= flow.date);
+
+            match (period, end) {
+                (Some(i), Some(end)) if flow.date < end => flows[i] += flow.amount,
+                _ => {
+                    return Err(anyhow!(
+                        "Cash flow at {} is outside of the periods of series '{}'",
+                        flow.date,
+                        ts.id
+                    ))
+                }
+            }
+        }
+
+        Ok(flows)
+    }
+}
+
+/// Future value at the end of the last period of `flows` made at the start of each
+/// period, compounded at `rate` per period.
+fn future_value(flows: &[f64], rate: f64) -> f64 {
+    let n = flows.len() as i32;
+
+    flows
+        .iter()
+        .enumerate()
+        .map(|(t, f)| f * (1.0 + rate).powi(n - t as i32))
+        .sum()
+}
+
+/// Rate per period at which `flows` grow to `value_at_end`, found by bisection.
+fn internal_rate_of_return(flows: &[f64], value_at_end: f64) -> Option<f64> {
+    let f = |rate| future_value(flows, rate) - value_at_end;
+
+    let mut low = -0.999;
+    let mut high = 1.0;
+    while f(high) * f(low) > 0.0 && high < 1e3 {
+        high *= 2.0;
+    }
+
+    if f(high) * f(low) > 0.0 {
+        return None;
+    }
+
+    for _ in 0..200 {
+        let middle = (low + high) / 2.0;
+
+        if f(middle) * f(low) > 0.0 {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    Some((low + high) / 2.0)
+}
+
+/// Path of the value of `ts` with the deposits and withdrawals of `cash_flows`.
+pub fn project(ts: &TimeSeries, cash_flows: &CashFlows) -> Result<Projection> {
+    let flows = cash_flows.flows(ts)?;
+
+    let mut value = 0.0;
+    let mut values = Vec::with_capacity(flows.len());
+    let mut growth = 1.0;
+    let mut invested_periods = 0;
+
+    for ((date, flow), r) in ts.dates.iter().zip(&flows).zip(&ts.returns) {
+        value += flow;
+
+        if value < -1e-9 {
+            return Err(anyhow!(
+                "Withdrawal at {} is larger than the value of series '{}'",
+                date,
+                ts.id
+            ));
+        }
+
+        if value > 0.0 {
+            growth *= 1.0 + r;
+            invested_periods += 1;
+        }
+
+        value *= 1.0 + r;
+        values.push(value);
+    }
+
+    let money_weighted_return = internal_rate_of_return(&flows, value);
+    let periods_per_year = ts.frequency.periods_per_year();
+
+    Ok(Projection {
+        dates: ts.dates.clone(),
+        total_invested: flows.iter().sum(),
+        flows,
+        values,
+        value_at_end: value,
+        money_weighted_return,
+        annualized_money_weighted_return: money_weighted_return
+            .map(|r| (1.0 + r).powf(periods_per_year) - 1.0),
+        time_weighted_return: growth - 1.0,
+        annualized_time_weighted_return: if invested_periods > 0 {
+            annualize_growth(growth, invested_periods, ts.frequency)
+        } else {
+            0.0
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::monthly;
+
+    #[test]
+    fn constant_return_has_same_money_and_time_weighted_returns() {
+        let ts = monthly("a", vec![0.01; 6]);
+        let cash_flows = CashFlows {
+            initial: 1000.0,
+            recurring: 100.0,
+            one_offs: vec![CashFlow {
+                date: NaiveDate::from_ymd_opt(2021, 3, 15).unwrap(),
+                amount: -300.0,
+            }],
+        };
+
+        let projection = project(&ts, &cash_flows).unwrap();
+
+        assert_eq!(
+            projection.flows,
+            vec![1000.0, 100.0, -200.0, 100.0, 100.0, 100.0]
+        );
+        assert!((projection.money_weighted_return.unwrap() - 0.01).abs() < 1e-9);
+        assert!((projection.time_weighted_return - (1.01_f64.powi(6) - 1.0)).abs() < 1e-12);
+        assert!((projection.value_at_end - future_value(&projection.flows, 0.01)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn money_weighted_return_depends_on_timing() {
+        // Most of the money is invested right before the loss.
+        let ts = monthly("a", vec![0.1, -0.1]);
+        let cash_flows = CashFlows {
+            initial: 100.0,
+            recurring: 1000.0,
+            one_offs: vec![],
+        };
+
+        let projection = project(&ts, &cash_flows).unwrap();
+
+        assert!((projection.time_weighted_return - (1.1 * 0.9 - 1.0)).abs() < 1e-12);
+        assert!(projection.money_weighted_return.unwrap() < -0.05);
+    }
+
+    #[test]
+    fn flows_outside_of_series_fail() {
+        let cash_flows = CashFlows {
+            initial: 100.0,
+            recurring: 0.0,
+            one_offs: vec![CashFlow {
+                date: NaiveDate::from_ymd_opt(2021, 3, 1).unwrap(),
+                amount: 10.0,
+            }],
+        };
+
+        assert!(project(&monthly("a", vec![0.01; 2]), &cash_flows).is_err());
+    }
+}
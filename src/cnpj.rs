@@ -0,0 +1,150 @@
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// CNPJ of a fund, with valid check digits. Parsed from its formatted form
+/// (`XX.XXX.XXX/XXXX-XX`), its 14 digits alone, or the form used in file names, with the
+/// slash replaced by an underscore (`XX.XXX.XXX_XXXX-XX`), and displayed formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cnpj([u8; 14]);
+
+/// Whether the 14 digits of a CNPJ end with their two check digits, computed with
+/// weights from 2 to 9 repeating from the right, modulo 11.
+fn valid_check_digits(digits: &[u8]) -> bool {
+    if digits.len() != 14 || digits.iter().all(|d| *d == digits[0]) {
+        return false;
+    }
+
+    let check_digit = |digits: &[u8]| {
+        let sum: u32 = digits
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, d)| *d as u32 * (2 + i as u32 % 8))
+            .sum();
+
+        match sum % 11 {
+            0 | 1 => 0,
+            r => 11 - r as u8,
+        }
+    };
+
+    check_digit(&digits[..12]) == digits[12] && check_digit(&digits[..13]) == digits[13]
+}
+
+impl Cnpj {
+    pub fn digits(&self) -> &[u8; 14] {
+        &self.0
+    }
+
+    /// Form used in file names, with the slash replaced by an underscore.
+    pub fn file_name(&self) -> String {
+        self.to_string().replace('/', "_")
+    }
+}
+
+impl FromStr for Cnpj {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Cnpj> {
+        const FORMATTED: &str = "00.000.000/0000-00";
+        const FILE_NAME: &str = "00.000.000_0000-00";
+
+        let s = s.trim();
+        let is_form = |template: &str| {
+            s.len() == template.len()
+                && s.chars().zip(template.chars()).all(|(c, t)| match t {
+                    '0' => c.is_ascii_digit(),
+                    _ => c == t,
+                })
+        };
+
+        if !(is_form(FORMATTED) || is_form(FILE_NAME) || is_form("00000000000000")) {
+            return Err(anyhow!(
+                "Invalid CNPJ '{}'. It should be in the form '{}', '{}' or have 14 digits",
+                s,
+                FORMATTED,
+                FILE_NAME
+            ));
+        }
+
+        let digits: Vec<u8> = s
+            .chars()
+            .filter_map(|c| c.to_digit(10).map(|d| d as u8))
+            .collect();
+
+        if !valid_check_digits(&digits) {
+            return Err(anyhow!("CNPJ '{}' has invalid check digits", s));
+        }
+
+        Ok(Cnpj(digits.try_into().expect("CNPJ has 14 digits")))
+    }
+}
+
+impl TryFrom<String> for Cnpj {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Cnpj> {
+        s.parse()
+    }
+}
+
+impl From<Cnpj> for String {
+    fn from(cnpj: Cnpj) -> String {
+        cnpj.to_string()
+    }
+}
+
+impl fmt::Display for Cnpj {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let d: String = self.0.iter().map(|d| char::from(b'0' + d)).collect();
+
+        write!(
+            f,
+            "{}.{}.{}/{}-{}",
+            &d[..2],
+            &d[2..5],
+            &d[5..8],
+            &d[8..12],
+            &d[12..]
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_form() {
+        let cnpj: Cnpj = "32.319.351/0001-56".parse().unwrap();
+
+        assert_eq!("32319351000156".parse::<Cnpj>().unwrap(), cnpj);
+        assert_eq!("32.319.351_0001-56".parse::<Cnpj>().unwrap(), cnpj);
+        assert_eq!(cnpj.to_string(), "32.319.351/0001-56");
+        assert_eq!(cnpj.file_name(), "32.319.351_0001-56");
+    }
+
+    #[test]
+    fn rejects_invalid_cnpjs() {
+        assert!("32.319.351/0001-57".parse::<Cnpj>().is_err());
+        assert!("11.111.111/1111-11".parse::<Cnpj>().is_err());
+        assert!("32.319.351/000156".parse::<Cnpj>().is_err());
+        assert!("3231935100015".parse::<Cnpj>().is_err());
+        assert!("00.000.000/0001-91".parse::<Cnpj>().is_ok());
+    }
+
+    #[test]
+    fn serializes_formatted() {
+        let cnpj: Cnpj = serde_json::from_str("\"21185984000100\"").unwrap();
+
+        assert_eq!(
+            serde_json::to_string(&cnpj).unwrap(),
+            "\"21.185.984/0001-00\""
+        );
+        assert!(serde_json::from_str::<Cnpj>("\"21185984000101\"").is_err());
+    }
+}
@@ -1,15 +1,49 @@
-use serde::{Deserialize, Serialize};
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{de, Deserialize, Deserializer, Serialize};
+
+use crate::benchmark::Benchmarks;
+use crate::cashflow::CashFlows;
+use crate::cnpj::Cnpj;
+use crate::costs::Costs;
+use crate::missing::MissingData;
+use crate::montecarlo::Sampling;
+use crate::planning::Distribution;
+use crate::portfolio::Frequency;
+use crate::risk::RiskMethod;
+use crate::source::RawSource;
+use crate::tax::Taxes;
+use crate::validation::Validation;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
+    pub preprocess: Preprocess,
+    pub validation: Validation,
+    pub benchmarks: Benchmarks,
+    pub missing_data: MissingData,
     pub funds_filters: FundsFilters,
     pub portfolio: Portfolio,
+    pub optimizer: Optimizer,
+    pub objective: Objective,
+    pub risk: Risk,
+    pub backtest: Backtest,
+    pub simulation: Simulation,
+    pub costs: Costs,
+    pub taxes: Taxes,
+    pub monte_carlo: MonteCarlo,
+    pub cash_flows: CashFlows,
+    pub planning: Planning,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Preprocess {
+    pub sources: Vec<RawSource>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FundsFilters {
-    pub include: Vec<String>,
-    pub exclude: Vec<String>,
+    pub include: Vec<Cnpj>,
+    pub exclude: Vec<Cnpj>,
     pub volatility_threshold: f64,
 }
 
@@ -19,6 +53,101 @@ pub struct Portfolio {
     pub from_date: String,
     pub to_date: String,
     pub split_granularity: f64,
+    #[serde(default)]
+    pub frequency: Frequency,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Optimizer {
+    pub frontier_points: usize,
+    pub validate_with_grid: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Sharpe,
+    Sortino,
+    Calmar,
+    Omega,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Objective {
+    pub metric: Metric,
+    pub minimum_acceptable_return: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Risk {
+    pub confidence_levels: Vec<f64>,
+    pub max_cvar: Option<f64>,
+    pub max_cvar_confidence: f64,
+    pub max_cvar_method: RiskMethod,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Window {
+    Rolling,
+    Expanding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rebalance {
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Backtest {
+    pub window: Window,
+    pub lookback: usize,
+    pub rebalance: Rebalance,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Simulation {
+    pub band: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MonteCarlo {
+    pub paths: usize,
+    pub horizon: usize,
+    pub sampling: Sampling,
+    pub block_size: usize,
+    pub seed: u64,
+    pub percentiles: Vec<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Planning {
+    pub target: f64,
+    #[serde(deserialize_with = "at_least_one")]
+    pub horizon: usize,
+    pub confidence: f64,
+    pub initial: f64,
+    pub distribution: Distribution,
+}
+
+impl Portfolio {
+    pub fn window(&self) -> Result<(NaiveDate, NaiveDate)> {
+        Ok((
+            NaiveDate::parse_from_str(&self.from_date, "%Y-%m-%d")?,
+            NaiveDate::parse_from_str(&self.to_date, "%Y-%m-%d")?,
+        ))
+    }
+}
+
+/// Count that must not be zero, e.g. the periods of a horizon.
+fn at_least_one<'de, D: Deserializer<'de>>(deserializer: D) -> Result<usize, D::Error> {
+    match usize::deserialize(deserializer)? {
+        0 => Err(de::Error::custom("should be at least 1")),
+        n => Ok(n),
+    }
 }
 
 pub fn get_config() -> Config {
@@ -27,3 +156,23 @@ pub fn get_config() -> Config {
 
     toml::from_str(&config).expect("Config should be a toml file with proper attributes")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planning_horizon_is_at_least_one() {
+        let planning = |horizon| {
+            toml::from_str::<Planning>(&format!(
+                "target = 1.0\nhorizon = {}\nconfidence = 0.9\ninitial = 0.0\ndistribution = \"historical\"",
+                horizon
+            ))
+        };
+
+        assert_eq!(planning(12).unwrap().horizon, 12);
+
+        let error = planning(0).unwrap_err().to_string();
+        assert!(error.contains("horizon") && error.contains("at least 1"));
+    }
+}
@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+
+use crate::cnpj::Cnpj;
+use crate::id::SeriesId;
+
+/// Exit fee charged on the amount redeemed from lots held for fewer than `max_periods`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExitFee {
+    pub max_periods: usize,
+    pub fee: f64,
+}
+
+/// Fees specific to a fund, identified by its CNPJ, formatted or not.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FundCosts {
+    pub cnpj: Cnpj,
+    #[serde(default)]
+    pub entry_fee: f64,
+    /// Schedule of exit fees by holding period. The first entry whose `max_periods`
+    /// exceeds the holding period applies, and none applies beyond the last one.
+    #[serde(default)]
+    pub exit_fees: Vec<ExitFee>,
+    /// Overrides the default proportional cost for this fund.
+    pub proportional: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Costs {
+    /// Amount invested at the start, against which fixed costs are charged.
+    pub initial_investment: f64,
+    /// Fraction of every amount bought or sold paid as brokerage or spread.
+    #[serde(default)]
+    pub proportional: f64,
+    /// Amount paid for every trade, regardless of its size.
+    #[serde(default)]
+    pub fixed_per_trade: f64,
+    #[serde(default)]
+    pub funds: Vec<FundCosts>,
+}
+
+impl Default for Costs {
+    fn default() -> Self {
+        Costs {
+            initial_investment: 1.0,
+            proportional: 0.0,
+            fixed_per_trade: 0.0,
+            funds: Vec::new(),
+        }
+    }
+}
+
+impl Costs {
+    fn fund(&self, id: &SeriesId) -> Option<&FundCosts> {
+        let cnpj = id.cnpj()?;
+
+        self.funds.iter().find(|f| f.cnpj == cnpj)
+    }
+
+    pub fn proportional(&self, id: &SeriesId) -> f64 {
+        self.fund(id)
+            .and_then(|f| f.proportional)
+            .unwrap_or(self.proportional)
+    }
+
+    pub fn entry_fee(&self, id: &SeriesId) -> f64 {
+        self.fund(id).map_or(0.0, |f| f.entry_fee)
+    }
+
+    pub fn exit_fee(&self, id: &SeriesId, holding_periods: usize) -> f64 {
+        self.fund(id)
+            .and_then(|f| {
+                f.exit_fees
+                    .iter()
+                    .find(|e| holding_periods < e.max_periods)
+                    .map(|e| e.fee)
+            })
+            .unwrap_or(0.0)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Lot {
+    bought: usize,
+    amount: f64,
+}
+
+/// Amounts held in each fund, kept as lots by the period they were bought at so that
+/// exit fees can be charged by holding period, redeeming the oldest lots first.
+#[derive(Debug, Clone)]
+pub struct Holdings {
+    ids: Vec<SeriesId>,
+    lots: Vec<Vec<Lot>>,
+}
+
+impl Holdings {
+    pub fn new(ids: Vec<SeriesId>) -> Holdings {
+        let lots = vec![Vec::new(); ids.len()];
+
+        Holdings { ids, lots }
+    }
+
+    pub fn values(&self) -> Vec<f64> {
+        self.lots
+            .iter()
+            .map(|lots| lots.iter().map(|l| l.amount).sum())
+            .collect()
+    }
+
+    pub fn value(&self) -> f64 {
+        self.values().iter().sum()
+    }
+
+    pub fn weights(&self) -> Vec<f64> {
+        let value = self.value();
+
+        self.values().iter().map(|v| v / value).collect()
+    }
+
+    pub fn apply_returns(&mut self, returns: &[f64]) {
+        for (lots, r) in self.lots.iter_mut().zip(returns) {
+            for lot in lots {
+                lot.amount *= 1.0 + r;
+            }
+        }
+    }
+
+    /// Redeems `amount` from fund `i` at `period`, returning the cash received.
+    fn sell(&mut self, i: usize, amount: f64, period: usize, costs: &Costs) -> f64 {
+        let id = &self.ids[i];
+        let mut remaining = amount;
+        let mut fees = costs.proportional(id) * amount + costs.fixed_per_trade;
+
+        for lot in self.lots[i].iter_mut() {
+            let sold = lot.amount.min(remaining);
+
+            fees += sold * costs.exit_fee(id, period - lot.bought);
+            lot.amount -= sold;
+            remaining -= sold;
+
+            if remaining <= 0.0 {
+                break;
+            }
+        }
+
+        self.lots[i].retain(|l| l.amount > 0.0);
+
+        (amount - fees).max(0.0)
+    }
+
+    /// Invests `cash` in fund `i` at `period`.
+    fn buy(&mut self, i: usize, cash: f64, period: usize, costs: &Costs) {
+        let id = &self.ids[i];
+        let fees = (costs.entry_fee(id) + costs.proportional(id)) * cash + costs.fixed_per_trade;
+
+        self.lots[i].push(Lot {
+            bought: period,
+            amount: (cash - fees).max(0.0),
+        });
+    }
+
+    /// Trades towards `target` weights at `period`, adding `cash` to the amounts
+    /// redeemed. Returns whether any trade was made.
+    pub fn rebalance(&mut self, target: &[f64], cash: f64, period: usize, costs: &Costs) -> bool {
+        let values = self.values();
+        let total = values.iter().sum::<f64>() + cash;
+        let tolerance = 1e-12 * total;
+
+        let mut cash = cash;
+        let mut purchases = vec![0.0; target.len()];
+        let mut traded = false;
+
+        for (i, (value, weight)) in values.iter().zip(target).enumerate() {
+            let difference = weight * total - value;
+
+            if difference < -tolerance {
+                cash += self.sell(i, -difference, period, costs);
+                traded = true;
+            } else if difference > tolerance {
+                purchases[i] = difference;
+            }
+        }
+
+        // Costs of the sales leave less cash than needed, so every purchase is scaled
+        // down by the same factor.
+        let needed = purchases.iter().sum::<f64>();
+
+        if needed > 0.0 {
+            for (i, purchase) in purchases.iter().enumerate() {
+                if *purchase > 0.0 {
+                    self.buy(i, purchase * cash / needed, period, costs);
+                    traded = true;
+                }
+            }
+        }
+
+        traded
+    }
+
+    /// Cash received by redeeming everything at `period`.
+    pub fn liquidate(&mut self, period: usize, costs: &Costs) -> f64 {
+        (0..self.ids.len())
+            .map(|i| {
+                let value = self.lots[i].iter().map(|l| l.amount).sum::<f64>();
+
+                if value > 0.0 {
+                    self.sell(i, value, period, costs)
+                } else {
+                    0.0
+                }
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn costs() -> Costs {
+        Costs {
+            initial_investment: 1000.0,
+            proportional: 0.001,
+            fixed_per_trade: 1.0,
+            funds: vec![FundCosts {
+                cnpj: "00.000.000/0001-91".parse().unwrap(),
+                entry_fee: 0.0,
+                exit_fees: vec![
+                    ExitFee {
+                        max_periods: 1,
+                        fee: 0.05,
+                    },
+                    ExitFee {
+                        max_periods: 12,
+                        fee: 0.01,
+                    },
+                ],
+                proportional: Some(0.0),
+            }],
+        }
+    }
+
+    #[test]
+    fn exit_fee_follows_schedule() {
+        let costs = costs();
+        let fund = SeriesId::from("00000000000191");
+        let other = SeriesId::from("other");
+
+        assert_eq!(costs.exit_fee(&fund, 0), 0.05);
+        assert_eq!(costs.exit_fee(&fund, 5), 0.01);
+        assert_eq!(costs.exit_fee(&fund, 12), 0.0);
+        assert_eq!(costs.exit_fee(&other, 0), 0.0);
+        assert_eq!(costs.proportional(&fund), 0.0);
+        assert_eq!(costs.proportional(&other), 0.001);
+    }
+
+    #[test]
+    fn rebalance_charges_costs() {
+        let costs = costs();
+        let mut holdings = Holdings::new(vec!["00.000.000/0001-91".into(), "b".into()]);
+
+        holdings.rebalance(&[0.5, 0.5], 1000.0, 0, &costs);
+
+        // Each purchase pays the fixed cost, and "b" also the proportional one.
+        assert_eq!(holdings.values(), vec![499.0, 498.5]);
+
+        holdings.apply_returns(&[0.1, 0.0]);
+        holdings.rebalance(&[0.5, 0.5], 0.0, 2, &costs);
+
+        // Sells 25.2 of the first fund, paying the fixed cost and a 1% exit fee.
+        let cash = 25.2 - 1.0 - 0.252;
+        let expected = 498.5 + cash - 1.0 - 0.001 * cash;
+
+        assert!((holdings.values()[0] - 523.7).abs() < 1e-9);
+        assert!((holdings.values()[1] - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sales_never_return_negative_cash() {
+        let costs = costs();
+        let mut holdings = Holdings::new(vec!["a".into()]);
+
+        holdings.rebalance(&[1.0], 1000.0, 0, &costs);
+        assert!((holdings.value() - 998.0).abs() < 1e-9);
+
+        // The fixed cost exceeds the amount sold.
+        assert_eq!(holdings.sell(0, 0.5, 1, &costs), 0.0);
+        assert!((holdings.value() - 997.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Months, NaiveDate};
+use polars::prelude::*;
+
+use crate::cnpj::Cnpj;
+use crate::id::SeriesId;
+
+/// Quota value of a fund at the end of a day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quota {
+    pub id: SeriesId,
+    pub date: NaiveDate,
+    pub value: f64,
+}
+
+/// Return of a fund from the previous quota to the one at `date`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyReturn {
+    pub id: SeriesId,
+    pub date: NaiveDate,
+    pub value: f64,
+}
+
+/// Return of a fund over the month starting at `month`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonthlyReturn {
+    pub id: SeriesId,
+    pub month: NaiveDate,
+    pub value: f64,
+}
+
+/// CNPJ column among the `columns` of a daily report. Files after CVM Resolution 175
+/// name it `CNPJ_FUNDO_CLASSE` instead of `CNPJ_FUNDO`.
+fn cnpj_column<'a>(columns: impl IntoIterator<Item = &'a str>) -> Result<&'static str> {
+    let columns = columns.into_iter().collect::<Vec<_>>();
+
+    ["CNPJ_FUNDO", "CNPJ_FUNDO_CLASSE"]
+        .into_iter()
+        .find(|c| columns.contains(c))
+        .ok_or(anyhow!("Daily report should have a CNPJ column"))
+}
+
+/// Reads a daily report file of the CVM open data (`inf_diario_fi_YYYYMM.csv`), which is
+/// semicolon-delimited and not always valid UTF-8.
+pub fn read_inf_diario(path: &Path) -> Result<DataFrame> {
+    let mut header = Vec::new();
+    BufReader::new(File::open(path)?).read_until(b'\n', &mut header)?;
+    let header = String::from_utf8_lossy(&header);
+
+    Ok(CsvReader::from_path(path)?
+        .with_separator(b';')
+        .with_encoding(CsvEncoding::LossyUtf8)
+        .has_header(true)
+        .with_columns(Some(vec![
+            cnpj_column(header.trim_end().split(';'))?.to_string(),
+            "DT_COMPTC".to_string(),
+            "VL_QUOTA".to_string(),
+        ]))
+        .finish()?)
+}
+
+/// Quotas of the `funds` in a daily report, or of every fund if `funds` is empty, in
+/// which case the rows with a malformed CNPJ are skipped and added to `skipped`, by
+/// their index in `df` and their CNPJ.
+pub fn quotas(
+    df: &DataFrame,
+    funds: &[Cnpj],
+    skipped: &mut Vec<(usize, String)>,
+) -> Result<Vec<Quota>> {
+    let cnpjs = df[cnpj_column(df.get_column_names())?].str()?;
+    let dates = df["DT_COMPTC"].cast(&DataType::String)?;
+    let dates = dates.str()?;
+    let values = df["VL_QUOTA"].cast(&DataType::Float64)?;
+    let values = values.f64()?;
+
+    let mut quotas = Vec::new();
+
+    for (i, ((cnpj, date), value)) in cnpjs.into_iter().zip(dates).zip(values).enumerate() {
+        let (Some(cnpj), Some(date), Some(value)) = (cnpj, date, value) else {
+            continue;
+        };
+
+        let Ok(cnpj) = cnpj.parse::<Cnpj>() else {
+            if funds.is_empty() {
+                skipped.push((i, cnpj.to_string()));
+            }
+            continue;
+        };
+
+        if !funds.is_empty() && !funds.contains(&cnpj) {
+            continue;
+        }
+
+        if value <= 0.0 {
+            return Err(anyhow!(
+                "Quota of fund {} at {} should be positive, but is {}",
+                cnpj,
+                date,
+                value
+            ));
+        }
+
+        quotas.push(Quota {
+            id: cnpj.into(),
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d")?,
+            value,
+        });
+    }
+
+    Ok(quotas)
+}
+
+pub(crate) fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1)
+        .expect("First day of the month is always valid")
+}
+
+/// Returns of each fund between consecutive quotas, leaving out the first quota. A single
+/// return spans the days between quotas, e.g. on holidays.
+pub fn daily_returns(quotas: &[Quota]) -> Vec<DailyReturn> {
+    let mut by_fund: BTreeMap<&SeriesId, BTreeMap<NaiveDate, f64>> = BTreeMap::new();
+
+    for quota in quotas {
+        by_fund
+            .entry(&quota.id)
+            .or_default()
+            .insert(quota.date, quota.value);
+    }
+
+    let mut returns = Vec::new();
+
+    for (id, days) in by_fund {
+        for ((_, previous), (date, current)) in days.iter().zip(days.iter().skip(1)) {
+            returns.push(DailyReturn {
+                id: id.clone(),
+                date: *date,
+                value: current / previous - 1.0,
+            });
+        }
+    }
+
+    returns
+}
+
+/// Returns of each fund between the last quotas of consecutive months. The first month
+/// of each fund, and the months right after a month without quotas, are left out since
+/// there is no quota at the end of the previous month to compare with.
+pub fn monthly_returns(quotas: &[Quota]) -> Vec<MonthlyReturn> {
+    let mut last_quotas: BTreeMap<&SeriesId, BTreeMap<NaiveDate, (NaiveDate, f64)>> =
+        BTreeMap::new();
+
+    for quota in quotas {
+        let months = last_quotas.entry(&quota.id).or_default();
+        let last = months
+            .entry(month_start(quota.date))
+            .or_insert((quota.date, quota.value));
+
+        if quota.date >= last.0 {
+            *last = (quota.date, quota.value);
+        }
+    }
+
+    let mut returns = Vec::new();
+
+    for (id, months) in last_quotas {
+        for ((previous_month, (_, previous)), (month, (_, current))) in
+            months.iter().zip(months.iter().skip(1))
+        {
+            if *previous_month + Months::new(1) == *month {
+                returns.push(MonthlyReturn {
+                    id: id.clone(),
+                    month: *month,
+                    value: current / previous - 1.0,
+                });
+            }
+        }
+    }
+
+    returns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const FILE: &str =
+        "TP_FUNDO;CNPJ_FUNDO;DT_COMPTC;VL_TOTAL;VL_QUOTA;VL_PATRIM_LIQ;CAPTC_DIA;RESG_DIA;NR_COTST
+FI;00.017.024/0001-53;2023-01-02;1000.0;1.10;1000.0;0;0;10
+FI;00.017.024/0001-53;2023-01-31;1000.0;1.00;1000.0;0;0;10
+FI;00.017.024/0001-53;2023-02-28;1000.0;1.02;1000.0;0;0;10
+FI;00.017.024/0001-53;2023-02-15;1000.0;1.50;1000.0;0;0;10
+FI;00.017.024/0001-53;2023-04-28;1000.0;1.03;1000.0;0;0;10
+FI;00.017.024/0001-53;2023-05-31;1000.0;1.0609;1000.0;0;0;10
+FI;11.111.111/0001-91;2023-01-31;1000.0;2.00;1000.0;0;0;10
+FI;11.111.111/0001-91;2023-02-28;1000.0;2.00;1000.0;0;0;10
+FI;11.111.111/0001-92;2023-02-28;1000.0;2.00;1000.0;0;0;10";
+
+    fn df() -> DataFrame {
+        CsvReader::new(Cursor::new(FILE))
+            .with_separator(b';')
+            .has_header(true)
+            .finish()
+            .unwrap()
+    }
+
+    #[test]
+    fn returns_between_last_quotas_of_consecutive_months() {
+        let quotas = quotas(&df(), &["00017024000153".parse().unwrap()], &mut Vec::new()).unwrap();
+        let returns = monthly_returns(&quotas);
+
+        let month = |m| NaiveDate::from_ymd_opt(2023, m, 1).unwrap();
+
+        assert_eq!(returns.len(), 2);
+        assert_eq!(returns[0].month, month(2));
+        assert!((returns[0].value - 0.02).abs() < 1e-12);
+        assert_eq!(returns[1].month, month(5));
+        assert!((returns[1].value - 0.03).abs() < 1e-12);
+    }
+
+    #[test]
+    fn returns_between_consecutive_quotas() {
+        let quotas = quotas(&df(), &["11111111000191".parse().unwrap()], &mut Vec::new()).unwrap();
+        let returns = daily_returns(&quotas);
+
+        assert_eq!(returns.len(), 1);
+        assert_eq!(
+            returns[0].date,
+            NaiveDate::from_ymd_opt(2023, 2, 28).unwrap()
+        );
+        assert_eq!(returns[0].value, 0.0);
+    }
+
+    #[test]
+    fn keeps_every_fund_without_filter() {
+        let mut skipped = Vec::new();
+        let quotas = quotas(&df(), &[], &mut skipped).unwrap();
+
+        // The row of the malformed CNPJ is skipped.
+        assert_eq!(quotas.len(), 8);
+        assert_eq!(monthly_returns(&quotas).len(), 3);
+        assert_eq!(skipped, vec![(8, "11.111.111/0001-92".to_string())]);
+    }
+}
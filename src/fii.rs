@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use polars::prelude::*;
+
+use crate::calendar::Calendar;
+use crate::cnpj::Cnpj;
+use crate::cvm::month_start;
+use crate::portfolio::{Frequency, TimeSeries};
+use crate::resample::resample;
+
+/// Monthly figures of a real estate fund (FII) from the CVM monthly reports, with
+/// percentages as fractions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiiReport {
+    pub cnpj: Cnpj,
+    pub month: NaiveDate,
+    pub name: Option<String>,
+    pub profitability: f64,
+    pub dividend_yield: Option<f64>,
+    pub patrimony: Option<f64>,
+    pub total_invested: Option<f64>,
+    pub total_liabilities: Option<f64>,
+}
+
+/// Reads a monthly report file of the CVM open data for FIIs
+/// (`inf_mensal_fii_{geral,complemento,ativo_passivo}_YYYY.csv`), which is
+/// semicolon-delimited and not always valid UTF-8.
+pub fn read_inf_mensal(path: &Path) -> Result<DataFrame> {
+    Ok(CsvReader::from_path(path)?
+        .with_separator(b';')
+        .with_encoding(CsvEncoding::LossyUtf8)
+        .has_header(true)
+        .infer_schema(None)
+        .finish()?)
+}
+
+fn strings(df: &DataFrame, name: &str) -> Result<Vec<Option<String>>> {
+    let column = df.column(name)?.cast(&DataType::String)?;
+
+    Ok(column
+        .str()?
+        .into_iter()
+        .map(|x| x.map(String::from))
+        .collect())
+}
+
+fn floats(df: &DataFrame, name: &str) -> Result<Vec<Option<f64>>> {
+    let column = df.column(name)?.cast(&DataType::Float64)?;
+
+    Ok(column.f64()?.into_iter().collect())
+}
+
+/// Floats of `name`, or none at all if the column is absent from `df`.
+fn optional_floats(df: &DataFrame, name: &str) -> Result<Vec<Option<f64>>> {
+    if df.get_column_names().contains(&name) {
+        floats(df, name)
+    } else {
+        Ok(vec![None; df.height()])
+    }
+}
+
+/// Row of the latest version of the report of each fund and month in `df`. Files after
+/// CVM Resolution 175 name the CNPJ column `CNPJ_Fundo_Classe` instead of `CNPJ_Fundo`.
+fn latest_rows(df: &DataFrame) -> Result<HashMap<(String, NaiveDate), usize>> {
+    let cnpj_column = ["CNPJ_Fundo", "CNPJ_Fundo_Classe"]
+        .into_iter()
+        .find(|c| df.get_column_names().contains(c))
+        .ok_or(anyhow!("Monthly report should have a CNPJ column"))?;
+
+    let cnpjs = strings(df, cnpj_column)?;
+    let dates = strings(df, "Data_Referencia")?;
+    let versions = optional_floats(df, "Versao")?;
+
+    let mut rows: HashMap<(String, NaiveDate), (usize, f64)> = HashMap::new();
+
+    for (i, ((cnpj, date), version)) in cnpjs.into_iter().zip(dates).zip(versions).enumerate() {
+        let (Some(cnpj), Some(date)) = (cnpj, date) else {
+            continue;
+        };
+
+        let month = month_start(NaiveDate::parse_from_str(&date, "%Y-%m-%d")?);
+        let version = version.unwrap_or(0.0);
+
+        let entry = rows.entry((cnpj, month)).or_insert((i, version));
+        if version >= entry.1 {
+            *entry = (i, version);
+        }
+    }
+
+    Ok(rows.into_iter().map(|(k, (i, _))| (k, i)).collect())
+}
+
+/// Reports of the `funds`, or of every fund if `funds` is empty, in which case the funds
+/// with a malformed CNPJ in the complement file are skipped and added to `skipped`, by
+/// the index of their row in `complement` and their CNPJ. The general, complement and asset and liability files are joined by fund
+/// and month, and months without profitability in the complement file are left out.
+pub fn reports(
+    general: &DataFrame,
+    complement: &DataFrame,
+    assets_and_liabilities: Option<&DataFrame>,
+    funds: &[Cnpj],
+    skipped: &mut Vec<(usize, String)>,
+) -> Result<Vec<FiiReport>> {
+    let names = strings(general, "Nome_Fundo")?;
+    let general_rows = latest_rows(general)?;
+
+    let profitability = floats(complement, "Percentual_Rentabilidade_Efetiva_Mes")?;
+    let dividend_yield = optional_floats(complement, "Percentual_Dividend_Yield_Mes")?;
+    let patrimony = optional_floats(complement, "Patrimonio_Liquido")?;
+
+    let (balance_rows, total_invested, total_liabilities) = match assets_and_liabilities {
+        Some(df) => (
+            latest_rows(df)?,
+            optional_floats(df, "Total_Investido")?,
+            optional_floats(df, "Total_Passivo")?,
+        ),
+        None => (HashMap::new(), Vec::new(), Vec::new()),
+    };
+
+    let mut reports = Vec::new();
+
+    for (key, i) in latest_rows(complement)? {
+        let Ok(fund) = key.0.parse::<Cnpj>() else {
+            if funds.is_empty() {
+                skipped.push((i, key.0.clone()));
+            }
+            continue;
+        };
+
+        if !funds.is_empty() && !funds.contains(&fund) {
+            continue;
+        }
+
+        let Some(fund_profitability) = profitability[i] else {
+            continue;
+        };
+
+        let balance = balance_rows.get(&key);
+
+        reports.push(FiiReport {
+            name: general_rows.get(&key).and_then(|j| names[*j].clone()),
+            profitability: fund_profitability,
+            dividend_yield: dividend_yield[i],
+            patrimony: patrimony[i],
+            total_invested: balance.and_then(|j| total_invested[*j]),
+            total_liabilities: balance.and_then(|j| total_liabilities[*j]),
+            cnpj: fund,
+            month: key.1,
+        });
+    }
+
+    reports.sort_by_key(|r| (r.cnpj, r.month));
+
+    Ok(reports)
+}
+
+/// Writes the reports of every source to the `fii.csv` at `path`, sorted by fund and
+/// month, failing if several sources report the same fund and month.
+pub fn write_reports(mut reports: Vec<FiiReport>, path: &Path) -> Result<()> {
+    reports.sort_by_key(|r| (r.cnpj, r.month));
+
+    if let Some(w) = reports
+        .windows(2)
+        .find(|w| (w[0].cnpj, w[0].month) == (w[1].cnpj, w[1].month))
+    {
+        return Err(anyhow!(
+            "FII '{}' has reports for {} in more than one source",
+            w[0].cnpj,
+            w[0].month
+        ));
+    }
+
+    let mut fii = df!(
+        "CNPJ_Fundo" => reports.iter().map(|r| r.cnpj.to_string()).collect::<Vec<_>>(),
+        "dt" => reports.iter().map(|r| r.month.to_string()).collect::<Vec<_>>(),
+        "name" => reports.iter().map(|r| r.name.clone()).collect::<Vec<_>>(),
+        "profitability" => reports.iter().map(|r| r.profitability).collect::<Vec<_>>(),
+        "dividend_yield" => reports.iter().map(|r| r.dividend_yield).collect::<Vec<_>>(),
+        "patrimony" => reports.iter().map(|r| r.patrimony).collect::<Vec<_>>(),
+        "total_invested" => reports.iter().map(|r| r.total_invested).collect::<Vec<_>>(),
+        "total_liabilities" => reports.iter().map(|r| r.total_liabilities).collect::<Vec<_>>(),
+    )?;
+
+    let file = std::fs::File::create(path)?;
+    CsvWriter::new(file).finish(&mut fii)?;
+
+    Ok(())
+}
+
+/// Dividend yields of each fund in `df`, with the columns of the `fii.csv` written by the
+/// preprocessing, as series identified by CNPJ at `frequency`. Months without a dividend
+/// yield are taken as paying none.
+pub fn dividends(df: &DataFrame, frequency: Frequency) -> Result<Vec<TimeSeries>> {
+    let cnpjs = strings(df, "CNPJ_Fundo")?;
+    let dates = strings(df, "dt")?;
+    let yields = floats(df, "dividend_yield")?;
+
+    let mut funds: BTreeMap<Cnpj, Vec<(NaiveDate, f64)>> = BTreeMap::new();
+
+    for ((cnpj, date), dividend_yield) in cnpjs.into_iter().zip(dates).zip(yields) {
+        let (Some(cnpj), Some(date)) = (cnpj, date) else {
+            return Err(anyhow!("Rows of 'fii.csv' should have a CNPJ and a date"));
+        };
+
+        funds.entry(cnpj.parse()?).or_default().push((
+            NaiveDate::parse_from_str(&date, "%Y-%m-%d")?,
+            dividend_yield.unwrap_or(0.0),
+        ));
+    }
+
+    funds
+        .into_iter()
+        .map(|(cnpj, rows)| {
+            let (dates, returns) = rows.into_iter().unzip();
+            let ts = TimeSeries::new(cnpj.to_string(), dates, returns)?
+                .with_frequency(Frequency::Monthly);
+
+            resample(&ts, frequency, &Calendar::anbima())
+        })
+        .collect()
+}
+
+/// Dividends in the `fii.csv` at `path`, or none if it was not written because no FII
+/// source was preprocessed.
+pub fn load_dividends(path: &Path, frequency: Frequency) -> Result<Vec<TimeSeries>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let df = CsvReader::from_path(path)?
+        .has_header(true)
+        .infer_schema(None)
+        .finish()?;
+
+    dividends(&df, frequency)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_dir;
+    use std::io::Cursor;
+
+    fn df(file: &str) -> DataFrame {
+        CsvReader::new(Cursor::new(file))
+            .with_separator(b';')
+            .has_header(true)
+            .infer_schema(None)
+            .finish()
+            .unwrap()
+    }
+
+    const GENERAL: &str = "CNPJ_Fundo;Data_Referencia;Versao;Nome_Fundo
+11.111.111/0001-91;2023-01-01;1;FII UM
+11.111.111/0001-91;2023-02-01;1;FII UM
+22.222.222/0001-91;2023-01-01;1;FII DOIS";
+
+    const COMPLEMENT: &str = "CNPJ_Fundo;Data_Referencia;Versao;Patrimonio_Liquido;Percentual_Rentabilidade_Efetiva_Mes;Percentual_Dividend_Yield_Mes
+11.111.111/0001-91;2023-01-01;1;1000000.0;0.010;0.008
+11.111.111/0001-91;2023-02-01;1;1010000.0;0.020;0.007
+11.111.111/0001-91;2023-02-01;2;1010000.0;0.015;0.007
+22.222.222/0001-91;2023-01-01;1;500000.0;;0.005
+33.333.333/0001;2023-01-01;1;500000.0;0.01;0.005";
+
+    const ASSETS_AND_LIABILITIES: &str =
+        "CNPJ_Fundo;Data_Referencia;Versao;Total_Investido;Total_Passivo
+11.111.111/0001-91;2023-01-01;1;900000.0;10000.0";
+
+    #[test]
+    fn joins_latest_versions() {
+        let mut skipped = Vec::new();
+        let reports = reports(
+            &df(GENERAL),
+            &df(COMPLEMENT),
+            Some(&df(ASSETS_AND_LIABILITIES)),
+            &[],
+            &mut skipped,
+        )
+        .unwrap();
+
+        // The second fund has no profitability, and the third a malformed CNPJ.
+        assert_eq!(reports.len(), 2);
+        assert_eq!(skipped, vec![(4, "33.333.333/0001".to_string())]);
+
+        assert_eq!(reports[0].name.as_deref(), Some("FII UM"));
+        assert_eq!(reports[0].dividend_yield, Some(0.008));
+        assert_eq!(reports[0].total_invested, Some(900000.0));
+        assert_eq!(reports[0].total_liabilities, Some(10000.0));
+
+        assert_eq!(
+            reports[1].month,
+            NaiveDate::from_ymd_opt(2023, 2, 1).unwrap()
+        );
+        assert_eq!(reports[1].profitability, 0.015);
+        assert_eq!(reports[1].total_invested, None);
+    }
+
+    #[test]
+    fn dividends_by_fund() {
+        let fii = df("CNPJ_Fundo;dt;dividend_yield
+11.111.111/0001-91;2023-01-01;0.008
+11.111.111/0001-91;2023-02-01;
+22.222.222/0001-91;2023-01-01;0.005");
+
+        let dividends = dividends(&fii, Frequency::Monthly).unwrap();
+
+        assert_eq!(dividends.len(), 2);
+        assert_eq!(dividends[0].id, "11.111.111/0001-91");
+        assert_eq!(dividends[0].returns, vec![0.008, 0.0]);
+        assert_eq!(dividends[1].returns, vec![0.005]);
+    }
+
+    #[test]
+    fn writes_reports_of_every_source() {
+        let all = reports(&df(GENERAL), &df(COMPLEMENT), None, &[], &mut Vec::new()).unwrap();
+        let path = temp_dir("fii_reports").join("fii.csv");
+
+        write_reports(all.clone(), &path).unwrap();
+        let dividends = load_dividends(&path, Frequency::Monthly).unwrap();
+
+        assert_eq!(dividends.len(), 1);
+        assert_eq!(dividends[0].returns, vec![0.008, 0.007]);
+
+        // A second source with the same fund and month.
+        let twice = all.iter().chain(&all[..1]).cloned().collect();
+        assert!(write_reports(twice, &path).is_err());
+    }
+
+    #[test]
+    fn filters_funds() {
+        let reports = reports(
+            &df(GENERAL),
+            &df(COMPLEMENT),
+            None,
+            &["22222222000191".parse().unwrap()],
+            &mut Vec::new(),
+        )
+        .unwrap();
+
+        assert!(reports.is_empty());
+    }
+}
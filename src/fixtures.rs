@@ -0,0 +1,47 @@
+//! Series and files shared by the tests of several modules.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use chrono::{Months, NaiveDate};
+
+use crate::portfolio::TimeSeries;
+
+/// First days of `n` consecutive months, starting at `from` (year and month).
+pub fn months_from(from: (i32, u32), n: usize) -> Vec<NaiveDate> {
+    let start = NaiveDate::from_ymd_opt(from.0, from.1, 1).unwrap();
+
+    (0..n as u32).map(|i| start + Months::new(i)).collect()
+}
+
+/// First days of `n` consecutive months, starting at January 2021.
+pub fn months(n: usize) -> Vec<NaiveDate> {
+    months_from((2021, 1), n)
+}
+
+/// Monthly series `id` with `returns` starting at `from` (year and month).
+pub fn monthly_from(id: &str, from: (i32, u32), returns: Vec<f64>) -> TimeSeries {
+    TimeSeries::new(id, months_from(from, returns.len()), returns).unwrap()
+}
+
+/// Monthly series `id` with `returns` starting at January 2021.
+pub fn monthly(id: &str, returns: Vec<f64>) -> TimeSeries {
+    monthly_from(id, (2021, 1), returns)
+}
+
+/// Fresh directory named after `test`, which is never shared with other tests or runs,
+/// even when they run in parallel.
+pub fn temp_dir(test: &str) -> PathBuf {
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!(
+        "investments_{}_{}_{}",
+        test,
+        std::process::id(),
+        CREATED.fetch_add(1, Ordering::Relaxed)
+    ));
+    _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
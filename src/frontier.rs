@@ -0,0 +1,85 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::id::SeriesId;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrontierPoint {
+    pub volatility: f64,
+    pub average: f64,
+    pub split: Vec<f64>,
+}
+
+/// Best Sharpe ratio among the splits of the grid and the one found by the optimizer,
+/// which should be at least as high.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GridValidation {
+    pub grid_sharpe_ratio: f64,
+    pub optimizer_sharpe_ratio: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EfficientFrontier {
+    pub funds: Vec<SeriesId>,
+    pub points: Vec<FrontierPoint>,
+    pub grid_validation: Option<GridValidation>,
+}
+
+/// Points of `(volatility, average)` not dominated by any other one, i.e. such that no
+/// other point has lower or equal volatility with higher average, sorted by volatility.
+///
+/// When several points share the same coordinates, only the first one is kept, along
+/// with its own split.
+pub fn extract_efficient_frontier(
+    volatilities: &[f64],
+    averages: &[f64],
+    splits: &[Vec<f64>],
+) -> Vec<FrontierPoint> {
+    let order = (0..volatilities.len()).sorted_by(|&i, &j| {
+        volatilities[i]
+            .partial_cmp(&volatilities[j])
+            .expect("No NaNs should exist for ordering")
+            .then(
+                averages[j]
+                    .partial_cmp(&averages[i])
+                    .expect("No NaNs should exist for ordering"),
+            )
+            .then(i.cmp(&j))
+    });
+
+    let mut frontier: Vec<FrontierPoint> = Vec::new();
+
+    for i in order {
+        let dominated = frontier
+            .last()
+            .is_some_and(|last| last.average >= averages[i]);
+
+        if !dominated {
+            frontier.push(FrontierPoint {
+                volatility: volatilities[i],
+                average: averages[i],
+                split: splits[i].clone(),
+            });
+        }
+    }
+
+    frontier
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_upper_boundary() {
+        let volatilities = [0.1, 0.2, 0.2, 0.3, 0.15, 0.3, 0.1];
+        let averages = [0.01, 0.03, 0.01, 0.02, 0.02, 0.04, 0.01];
+        let splits = (0..7).map(|i| vec![i as f64]).collect_vec();
+
+        let frontier = extract_efficient_frontier(&volatilities, &averages, &splits);
+
+        let kept = frontier.iter().map(|p| p.split[0] as usize).collect_vec();
+
+        assert_eq!(kept, vec![0, 4, 1, 5]);
+    }
+}
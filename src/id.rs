@@ -0,0 +1,111 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cnpj::Cnpj;
+
+/// Identifier of a series: the CNPJ of a fund, or the name of a benchmark or of a
+/// series derived from others. Parsed from text as a fund whenever it is a valid CNPJ,
+/// and displayed with the CNPJ formatted.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum SeriesId {
+    Fund(Cnpj),
+    Named(String),
+}
+
+impl SeriesId {
+    /// CNPJ of the fund of the series, or none for benchmarks and other series.
+    pub fn cnpj(&self) -> Option<Cnpj> {
+        match self {
+            SeriesId::Fund(cnpj) => Some(*cnpj),
+            SeriesId::Named(_) => None,
+        }
+    }
+}
+
+impl From<&str> for SeriesId {
+    fn from(s: &str) -> SeriesId {
+        match s.parse() {
+            Ok(cnpj) => SeriesId::Fund(cnpj),
+            Err(_) => SeriesId::Named(s.to_string()),
+        }
+    }
+}
+
+impl From<String> for SeriesId {
+    fn from(s: String) -> SeriesId {
+        s.as_str().into()
+    }
+}
+
+impl From<Cnpj> for SeriesId {
+    fn from(cnpj: Cnpj) -> SeriesId {
+        SeriesId::Fund(cnpj)
+    }
+}
+
+impl From<SeriesId> for String {
+    fn from(id: SeriesId) -> String {
+        id.to_string()
+    }
+}
+
+/// A series is equal to the text it is displayed as, with the CNPJ of funds formatted.
+impl PartialEq<str> for SeriesId {
+    fn eq(&self, other: &str) -> bool {
+        match self {
+            SeriesId::Fund(cnpj) => cnpj.to_string() == other,
+            SeriesId::Named(name) => name == other,
+        }
+    }
+}
+
+impl PartialEq<&str> for SeriesId {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for SeriesId {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl fmt::Display for SeriesId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SeriesId::Fund(cnpj) => cnpj.fmt(f),
+            SeriesId::Named(name) => name.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_funds_and_names() {
+        let fund: SeriesId = "32319351000156".into();
+
+        assert_eq!(fund, SeriesId::Fund("32.319.351/0001-56".parse().unwrap()));
+        assert_eq!(fund.to_string(), "32.319.351/0001-56");
+        assert_eq!(fund, "32.319.351/0001-56");
+        assert_ne!(fund, "32319351000156");
+        assert_eq!(SeriesId::from("cdi"), "cdi");
+        assert_eq!(SeriesId::from("32.319.351/0001-57").cnpj(), None);
+    }
+
+    #[test]
+    fn serializes_as_text() {
+        let ids: Vec<SeriesId> = serde_json::from_str("[\"21185984000100\", \"cdi\"]").unwrap();
+
+        assert_eq!(ids[0].cnpj(), Some("21.185.984/0001-00".parse().unwrap()));
+        assert_eq!(
+            serde_json::to_string(&ids).unwrap(),
+            "[\"21.185.984/0001-00\",\"cdi\"]"
+        );
+    }
+}
@@ -1,2 +1,26 @@
+pub mod backtest;
+pub mod benchmark;
+pub mod calendar;
+pub mod cashflow;
+pub mod cnpj;
 pub mod config;
+pub mod costs;
+pub mod cvm;
+pub mod fii;
+#[cfg(test)]
+mod fixtures;
+pub mod frontier;
+pub mod id;
+pub mod missing;
+pub mod montecarlo;
+pub mod objective;
+pub mod optimizer;
+pub mod planning;
 pub mod portfolio;
+pub mod resample;
+pub mod risk;
+pub mod selection;
+pub mod simulation;
+pub mod source;
+pub mod tax;
+pub mod validation;
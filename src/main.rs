@@ -1,12 +1,14 @@
 mod bin;
 
 use anyhow::Result;
-use bin::{outputs, preprocess, timeseries};
+use bin::{backtest, outputs, planning, preprocess, timeseries};
 
 fn main() -> Result<()> {
     preprocess::main()?;
     timeseries::main()?;
     outputs::main()?;
+    backtest::main()?;
+    planning::main()?;
 
     Ok(())
 }
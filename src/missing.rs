@@ -0,0 +1,349 @@
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::Calendar;
+use crate::id::SeriesId;
+use crate::portfolio::{Frequency, TimeSeries};
+use crate::resample::resample;
+
+/// What to do with the returns missing from a series, either because their value is
+/// absent or because a period between its first and last ones has no row.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingDataPolicy {
+    /// Drops the periods with a missing return from every fund and benchmark.
+    #[default]
+    DropPeriod,
+    /// Drops the funds and benchmarks with any missing return.
+    DropFund,
+    /// Repeats the last quota, i.e. fills the missing returns with zero.
+    ForwardFill,
+    /// Fills the missing returns linearly between the nearest present ones.
+    Interpolate,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MissingData {
+    pub policy: MissingDataPolicy,
+}
+
+/// Returns of a series at its own frequency, some of which may be missing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observations {
+    pub id: SeriesId,
+    pub dates: Vec<NaiveDate>,
+    pub values: Vec<Option<f64>>,
+    pub frequency: Frequency,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingDataAction {
+    Filled,
+    DroppedFund,
+    DroppedPeriods,
+}
+
+/// Dates, at the frequency of the series, of the returns missing from it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissingReturns {
+    pub id: SeriesId,
+    pub dates: Vec<NaiveDate>,
+    pub action: MissingDataAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissingDataReport {
+    pub policy: MissingDataPolicy,
+    pub series: Vec<MissingReturns>,
+    /// Periods, at the resampled frequency, dropped from every series.
+    pub dropped_periods: Vec<NaiveDate>,
+}
+
+/// Inserts the periods without a row between the first and last dates of `observations`
+/// as missing values. Daily returns span the days since the previous row, as the ones
+/// between quotas or index levels do, so days without a row are not missing.
+fn complete(observations: &Observations) -> Vec<(NaiveDate, Option<f64>)> {
+    let frequency = observations.frequency;
+
+    if frequency == Frequency::Daily {
+        return observations
+            .dates
+            .iter()
+            .copied()
+            .zip(observations.values.iter().copied())
+            .collect();
+    }
+
+    let mut completed = Vec::with_capacity(observations.dates.len());
+
+    for (i, (date, value)) in observations
+        .dates
+        .iter()
+        .zip(&observations.values)
+        .enumerate()
+    {
+        if let Some(previous) = i.checked_sub(1).map(|i| observations.dates[i]) {
+            let mut gap = frequency.next_period(previous);
+            while gap < *date {
+                completed.push((gap, None));
+                gap = frequency.next_period(gap);
+            }
+        }
+
+        completed.push((*date, *value));
+    }
+
+    completed
+}
+
+/// Missing values filled according to `policy`. The values filled for the policies that
+/// drop them are placeholders, so that the series can still be resampled.
+fn fill(values: &[Option<f64>], policy: MissingDataPolicy) -> Vec<f64> {
+    if policy != MissingDataPolicy::Interpolate {
+        return values.iter().map(|v| v.unwrap_or(0.0)).collect();
+    }
+
+    let present: Vec<(usize, f64)> = values
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| v.map(|v| (i, v)))
+        .collect();
+
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            if let Some(v) = v {
+                return *v;
+            }
+
+            let after = present.partition_point(|(j, _)| *j < i);
+            match (after.checked_sub(1).map(|p| present[p]), present.get(after)) {
+                (Some((j, a)), Some((k, b))) => a + (b - a) * (i - j) as f64 / (k - j) as f64,
+                (Some((_, a)), None) => a,
+                (None, Some((_, b))) => *b,
+                (None, None) => 0.0,
+            }
+        })
+        .collect()
+}
+
+/// Time series of each of `observations` at `frequency`, with their missing returns
+/// handled according to `missing_data`, along with a report of what was filled or dropped.
+/// Fails instead of dropping any of the `required` series, such as the benchmarks used.
+pub fn handle_missing(
+    observations: &[Observations],
+    missing_data: &MissingData,
+    frequency: Frequency,
+    calendar: &Calendar,
+    required: &[String],
+) -> Result<(Vec<TimeSeries>, MissingDataReport)> {
+    let policy = missing_data.policy;
+
+    let mut timeseries = Vec::with_capacity(observations.len());
+    let mut series = Vec::new();
+    let mut dropped_periods = BTreeSet::new();
+
+    for o in observations {
+        let (dates, values): (Vec<_>, Vec<_>) = complete(o).into_iter().unzip();
+        let missing: Vec<NaiveDate> = dates
+            .iter()
+            .zip(&values)
+            .filter(|(_, v)| v.is_none())
+            .map(|(d, _)| *d)
+            .collect();
+
+        let action = match policy {
+            MissingDataPolicy::DropPeriod => MissingDataAction::DroppedPeriods,
+            MissingDataPolicy::DropFund => MissingDataAction::DroppedFund,
+            MissingDataPolicy::ForwardFill | MissingDataPolicy::Interpolate => {
+                MissingDataAction::Filled
+            }
+        };
+
+        if !missing.is_empty() {
+            if policy == MissingDataPolicy::DropFund && required.iter().any(|r| o.id == *r) {
+                return Err(anyhow!(
+                    "The 'drop_fund' missing data policy would drop '{}', which is a configured benchmark, as it has missing returns for: {}",
+                    o.id,
+                    missing.iter().map(|d| d.to_string()).collect::<Vec<_>>().join(", ")
+                ));
+            }
+
+            if policy == MissingDataPolicy::DropPeriod {
+                dropped_periods.extend(missing.iter().map(|d| frequency.period_start(*d)));
+            }
+
+            series.push(MissingReturns {
+                id: o.id.clone(),
+                dates: missing,
+                action,
+            });
+
+            if policy == MissingDataPolicy::DropFund {
+                continue;
+            }
+        }
+
+        let ts = TimeSeries::new(o.id.clone(), dates, fill(&values, policy))?
+            .with_frequency(o.frequency);
+
+        timeseries.push(resample(&ts, frequency, calendar)?);
+    }
+
+    let timeseries = timeseries
+        .into_iter()
+        .map(|ts| {
+            let (dates, returns) = ts
+                .dates
+                .iter()
+                .zip(&ts.returns)
+                .filter(|(d, _)| !dropped_periods.contains(d))
+                .unzip();
+
+            Ok(TimeSeries::new(ts.id.clone(), dates, returns)?.with_frequency(ts.frequency))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let report = MissingDataReport {
+        policy,
+        series,
+        dropped_periods: dropped_periods.into_iter().collect(),
+    };
+
+    Ok((timeseries, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cvm::{daily_returns, Quota};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// Monthly observations in 2022 with a row for each of `rows`, by month.
+    fn monthly(id: &str, rows: &[(u32, Option<f64>)]) -> Observations {
+        Observations {
+            id: id.into(),
+            dates: rows.iter().map(|(m, _)| date(2022, *m, 1)).collect(),
+            values: rows.iter().map(|(_, v)| *v).collect(),
+            frequency: Frequency::Monthly,
+        }
+    }
+
+    fn handle(
+        policy: MissingDataPolicy,
+        required: &[String],
+    ) -> Result<(Vec<TimeSeries>, MissingDataReport)> {
+        // The second fund has a null in March and no row for May.
+        let observations = [
+            monthly("a", &(1..=6).map(|m| (m, Some(0.01))).collect::<Vec<_>>()),
+            monthly(
+                "b",
+                &[
+                    (1, Some(0.01)),
+                    (2, Some(0.02)),
+                    (3, None),
+                    (4, Some(0.04)),
+                    (6, Some(0.08)),
+                ],
+            ),
+        ];
+
+        handle_missing(
+            &observations,
+            &MissingData { policy },
+            Frequency::Monthly,
+            &Calendar::anbima(),
+            required,
+        )
+    }
+
+    #[test]
+    fn drops_periods_from_every_series() {
+        let (timeseries, report) = handle(MissingDataPolicy::DropPeriod, &[]).unwrap();
+
+        assert_eq!(
+            report.dropped_periods,
+            vec![date(2022, 3, 1), date(2022, 5, 1)]
+        );
+        assert_eq!(report.series.len(), 1);
+        assert_eq!(report.series[0].dates, report.dropped_periods);
+        assert!(timeseries.iter().all(|ts| ts.dates.len() == 4));
+        assert_eq!(timeseries[1].returns, vec![0.01, 0.02, 0.04, 0.08]);
+    }
+
+    #[test]
+    fn daily_returns_span_days_without_quotas() {
+        // No quota on Wednesday 2022-06-15, so the return of Thursday spans two days.
+        let quotas = [(1, 1.0), (14, 1.01), (16, 1.0302), (30, 1.0302)]
+            .iter()
+            .map(|(day, value)| Quota {
+                id: "11.111.111/0001-91".into(),
+                date: date(2022, 6, *day),
+                value: *value,
+            })
+            .collect::<Vec<_>>();
+        let returns = daily_returns(&quotas);
+
+        let observations = Observations {
+            id: "11.111.111/0001-91".into(),
+            dates: returns.iter().map(|r| r.date).collect(),
+            values: returns.iter().map(|r| Some(r.value)).collect(),
+            frequency: Frequency::Daily,
+        };
+
+        for policy in [
+            MissingDataPolicy::DropPeriod,
+            MissingDataPolicy::Interpolate,
+        ] {
+            let (timeseries, report) = handle_missing(
+                std::slice::from_ref(&observations),
+                &MissingData { policy },
+                Frequency::Daily,
+                &Calendar::anbima(),
+                &[],
+            )
+            .unwrap();
+
+            assert!(report.series.is_empty());
+            assert!((timeseries[0].calculate_value_at_end(1.0) - 1.0302).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn drops_funds() {
+        let (timeseries, report) = handle(MissingDataPolicy::DropFund, &[]).unwrap();
+
+        assert_eq!(timeseries.len(), 1);
+        assert_eq!(timeseries[0].id, "a");
+        assert_eq!(report.series[0].action, MissingDataAction::DroppedFund);
+
+        let Err(error) = handle(MissingDataPolicy::DropFund, &["b".to_string()]) else {
+            panic!("A required series should not be dropped");
+        };
+        assert!(error.to_string().contains("'drop_fund'"));
+    }
+
+    #[test]
+    fn fills_missing_returns() {
+        let (timeseries, report) = handle(MissingDataPolicy::ForwardFill, &[]).unwrap();
+        assert_eq!(
+            timeseries[1].returns,
+            vec![0.01, 0.02, 0.0, 0.04, 0.0, 0.08]
+        );
+        assert_eq!(report.series[0].action, MissingDataAction::Filled);
+
+        let (timeseries, _) = handle(MissingDataPolicy::Interpolate, &[]).unwrap();
+        let expected = [0.01, 0.02, 0.03, 0.04, 0.06, 0.08];
+        for (r, e) in timeseries[1].returns.iter().zip(expected) {
+            assert!((r - e).abs() < 1e-12);
+        }
+    }
+}
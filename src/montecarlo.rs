@@ -0,0 +1,361 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use statrs::distribution::Normal;
+
+use crate::benchmark::at_dates;
+use crate::config::MonteCarlo;
+use crate::id::SeriesId;
+use crate::portfolio::{align, covariance_matrix, TimeSeries};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Sampling {
+    /// Returns drawn from a multivariate normal with the historical means and covariance.
+    Normal,
+    /// Blocks of consecutive historical periods drawn with replacement, wrapping around
+    /// the end of the history, which keeps the correlation across funds and time.
+    Bootstrap,
+}
+
+/// Simulated returns of a benchmark, one vector per path.
+pub struct BenchmarkPaths {
+    pub id: SeriesId,
+    pub paths: Vec<Vec<f64>>,
+}
+
+/// Simulated returns of the portfolio, one vector per path, and of the benchmarks.
+pub struct Paths {
+    pub portfolio: Vec<Vec<f64>>,
+    pub benchmarks: Vec<BenchmarkPaths>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Percentile {
+    pub percentile: f64,
+    pub values: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkOutlook {
+    pub id: SeriesId,
+    pub median: Vec<f64>,
+    pub probability_of_beating: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonteCarloReport {
+    pub sampling: Sampling,
+    pub paths: usize,
+    pub horizon: usize,
+    pub dates: Vec<NaiveDate>,
+    /// Value of an initial investment of 1 at the end of each period, by percentile.
+    pub percentiles: Vec<Percentile>,
+    pub expected_value_at_end: f64,
+    pub probability_of_loss: f64,
+    pub benchmarks: Vec<BenchmarkOutlook>,
+}
+
+/// Lower triangular `l` with `l * l' = matrix`. Directions with no variance are left
+/// with zero columns, so singular covariance matrices are accepted.
+fn cholesky(matrix: &[Vec<f64>]) -> Vec<Vec<f64>> {
+    let n = matrix.len();
+    let mut l = vec![vec![0.0; n]; n];
+
+    for i in 0..n {
+        for j in 0..=i {
+            let s = matrix[i][j] - (0..j).map(|k| l[i][k] * l[j][k]).sum::<f64>();
+
+            if i == j {
+                l[i][i] = s.max(0.0).sqrt();
+            } else if l[j][j] > 0.0 {
+                l[i][j] = s / l[j][j];
+            }
+        }
+    }
+
+    l
+}
+
+enum Sampler {
+    Normal {
+        means: Vec<f64>,
+        factor: Vec<Vec<f64>>,
+        normal: Normal,
+    },
+    Bootstrap {
+        history: Vec<Vec<f64>>,
+        block_size: usize,
+    },
+}
+
+impl Sampler {
+    /// Returns of every series at each of `horizon` periods.
+    fn draw(&self, rng: &mut StdRng, horizon: usize) -> Vec<Vec<f64>> {
+        match self {
+            Sampler::Normal {
+                means,
+                factor,
+                normal,
+            } => (0..horizon)
+                .map(|_| {
+                    let z = (0..means.len())
+                        .map(|_| rng.sample(normal))
+                        .collect::<Vec<_>>();
+
+                    means
+                        .iter()
+                        .zip(factor)
+                        .map(|(m, row)| m + row.iter().zip(&z).map(|(a, b)| a * b).sum::<f64>())
+                        .collect()
+                })
+                .collect(),
+            Sampler::Bootstrap {
+                history,
+                block_size,
+            } => {
+                let n = history.len();
+                let mut periods = Vec::with_capacity(horizon);
+
+                while periods.len() < horizon {
+                    let start = rng.gen_range(0..n);
+
+                    periods.extend((start..start + block_size).map(|t| history[t % n].clone()));
+                }
+                periods.truncate(horizon);
+
+                periods
+            }
+        }
+    }
+}
+
+/// Percentile `p` (between 0 and 1) of sorted `values`, interpolating linearly.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let position = p * (sorted.len() - 1) as f64;
+    let (low, high) = (position.floor() as usize, position.ceil() as usize);
+
+    sorted[low] + (position - low as f64) * (sorted[high] - sorted[low])
+}
+
+/// Simulates `settings.paths` paths of `settings.horizon` periods of the returns of
+/// `funds` held at `split`, rebalanced at every period, together with `benchmarks`.
+pub fn sample_paths(
+    funds: &[TimeSeries],
+    benchmarks: &[TimeSeries],
+    split: &[f64],
+    settings: &MonteCarlo,
+) -> Result<Paths> {
+    if funds.len() != split.len() {
+        return Err(anyhow!("'funds' and 'split' have different lengths"));
+    }
+
+    let dates = align(funds)?;
+    if dates.len() < 2 {
+        return Err(anyhow!(
+            "At least two periods are needed for the simulation"
+        ));
+    }
+
+    let frequency = funds.first().map(|f| f.frequency).unwrap_or_default();
+
+    let mut series = funds.to_vec();
+    for benchmark in benchmarks {
+        series.push(at_dates(benchmark, &dates)?.with_frequency(frequency));
+    }
+
+    let mut rng = StdRng::seed_from_u64(settings.seed);
+
+    let sampler = match settings.sampling {
+        Sampling::Normal => Sampler::Normal {
+            means: series.iter().map(|s| s.average_returns()).collect(),
+            factor: cholesky(&covariance_matrix(&series)?),
+            normal: Normal::new(0.0, 1.0)?,
+        },
+        Sampling::Bootstrap => Sampler::Bootstrap {
+            history: (0..dates.len())
+                .map(|t| series.iter().map(|s| s.returns[t]).collect())
+                .collect(),
+            block_size: settings.block_size.max(1),
+        },
+    };
+
+    let mut portfolio = Vec::with_capacity(settings.paths);
+    let mut benchmark_paths = benchmarks
+        .iter()
+        .map(|b| BenchmarkPaths {
+            id: b.id.clone(),
+            paths: Vec::with_capacity(settings.paths),
+        })
+        .collect::<Vec<_>>();
+
+    for _ in 0..settings.paths {
+        let periods = sampler.draw(&mut rng, settings.horizon);
+
+        portfolio.push(
+            periods
+                .iter()
+                .map(|r| r.iter().zip(split).map(|(a, w)| a * w).sum())
+                .collect(),
+        );
+
+        for (i, benchmark) in benchmark_paths.iter_mut().enumerate() {
+            benchmark
+                .paths
+                .push(periods.iter().map(|r| r[split.len() + i]).collect());
+        }
+    }
+
+    Ok(Paths {
+        portfolio,
+        benchmarks: benchmark_paths,
+    })
+}
+
+fn values(returns: &[f64]) -> Vec<f64> {
+    returns
+        .iter()
+        .scan(1.0, |value, r| {
+            *value *= 1.0 + r;
+            Some(*value)
+        })
+        .collect()
+}
+
+/// Percentiles along time, probabilities of beating each benchmark and of loss at the
+/// end of the horizon, with `dates` the start of each simulated period.
+pub fn report(
+    paths: &Paths,
+    settings: &MonteCarlo,
+    dates: Vec<NaiveDate>,
+) -> Result<MonteCarloReport> {
+    if paths.portfolio.is_empty() {
+        return Err(anyhow!("No paths were simulated"));
+    }
+
+    let portfolio = paths
+        .portfolio
+        .iter()
+        .map(|r| values(r))
+        .collect::<Vec<_>>();
+    let sorted_at = |values: &[Vec<f64>], t: usize| {
+        let mut at = values.iter().map(|v| v[t]).collect::<Vec<_>>();
+        at.sort_by(|a, b| a.partial_cmp(b).expect("No NaNs should exist for ordering"));
+        at
+    };
+
+    let sorted_portfolio = (0..settings.horizon)
+        .map(|t| sorted_at(&portfolio, t))
+        .collect::<Vec<_>>();
+
+    let percentiles = settings
+        .percentiles
+        .iter()
+        .map(|p| Percentile {
+            percentile: *p,
+            values: sorted_portfolio.iter().map(|s| percentile(s, *p)).collect(),
+        })
+        .collect();
+
+    let n = portfolio.len() as f64;
+    let at_end = |v: &Vec<f64>| v.last().copied().unwrap_or(1.0);
+
+    let benchmarks = paths
+        .benchmarks
+        .iter()
+        .map(|b| {
+            let benchmark = b.paths.iter().map(|r| values(r)).collect::<Vec<_>>();
+
+            BenchmarkOutlook {
+                id: b.id.clone(),
+                median: (0..settings.horizon)
+                    .map(|t| percentile(&sorted_at(&benchmark, t), 0.5))
+                    .collect(),
+                probability_of_beating: portfolio
+                    .iter()
+                    .zip(&benchmark)
+                    .filter(|(p, b)| at_end(p) > at_end(b))
+                    .count() as f64
+                    / n,
+            }
+        })
+        .collect();
+
+    Ok(MonteCarloReport {
+        sampling: settings.sampling,
+        paths: settings.paths,
+        horizon: settings.horizon,
+        dates,
+        percentiles,
+        expected_value_at_end: portfolio.iter().map(at_end).sum::<f64>() / n,
+        probability_of_loss: portfolio.iter().filter(|p| at_end(p) < 1.0).count() as f64 / n,
+        benchmarks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::monthly;
+
+    fn settings(sampling: Sampling) -> MonteCarlo {
+        MonteCarlo {
+            paths: 2000,
+            horizon: 12,
+            sampling,
+            block_size: 3,
+            seed: 42,
+            percentiles: vec![0.05, 0.5, 0.95],
+        }
+    }
+
+    #[test]
+    fn cholesky_factors() {
+        let matrix = vec![vec![4.0, 2.0], vec![2.0, 5.0]];
+        let l = cholesky(&matrix);
+
+        assert_eq!(l, vec![vec![2.0, 0.0], vec![1.0, 2.0]]);
+    }
+
+    #[test]
+    fn bootstrap_only_draws_historical_periods() {
+        let a = monthly("a", vec![0.01, 0.02, 0.03, 0.04]);
+        let cdi = monthly("cdi", vec![0.005; 4]);
+
+        let paths = sample_paths(&[a], &[cdi], &[1.0], &settings(Sampling::Bootstrap)).unwrap();
+
+        assert_eq!(paths.portfolio.len(), 2000);
+        assert!(paths
+            .portfolio
+            .iter()
+            .flatten()
+            .all(|r| [0.01, 0.02, 0.03, 0.04].contains(r)));
+    }
+
+    #[test]
+    fn normal_paths_match_moments() {
+        let a = monthly("a", vec![0.01, 0.03, 0.01, 0.03]);
+        let cdi = monthly("cdi", vec![0.005; 4]);
+
+        let settings = settings(Sampling::Normal);
+        let paths = sample_paths(&[a], &[cdi], &[1.0], &settings).unwrap();
+
+        let all = paths.portfolio.iter().flatten().collect::<Vec<_>>();
+        let mean = all.iter().copied().sum::<f64>() / all.len() as f64;
+
+        assert!((mean - 0.02).abs() < 1e-3);
+        assert!(paths.benchmarks[0]
+            .paths
+            .iter()
+            .flatten()
+            .all(|r| (r - 0.005).abs() < 1e-12));
+
+        let dates = vec![NaiveDate::from_ymd_opt(2020, 5, 1).unwrap(); settings.horizon];
+        let report = report(&paths, &settings, dates).unwrap();
+
+        assert!(report.benchmarks[0].probability_of_beating > 0.99);
+        assert_eq!(report.probability_of_loss, 0.0);
+        assert!(report.percentiles[0].values[11] < report.percentiles[2].values[11]);
+    }
+}
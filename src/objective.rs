@@ -0,0 +1,154 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+
+use crate::config::{Config, Metric, Objective, Risk};
+use crate::optimizer::{efficient_frontier, max_sharpe, min_variance};
+use crate::portfolio::{Frequency, Portfolio, PortfolioStatistics, TimeSeries};
+use crate::simulation::RebalancePolicy;
+
+pub struct Candidates {
+    pub max_sharpe: Vec<f64>,
+    pub min_variance: Vec<f64>,
+    pub frontier: Vec<Vec<f64>>,
+}
+
+impl Candidates {
+    pub fn all(&self) -> Vec<Vec<f64>> {
+        let mut all = self.frontier.clone();
+        all.push(self.max_sharpe.clone());
+        all.push(self.min_variance.clone());
+
+        all
+    }
+}
+
+pub fn optimize(statistics: &PortfolioStatistics, frontier_points: usize) -> Candidates {
+    Candidates {
+        max_sharpe: max_sharpe(&statistics.excess_means, &statistics.excess_covariance),
+        min_variance: min_variance(&statistics.covariance),
+        frontier: efficient_frontier(&statistics.means, &statistics.covariance, frontier_points),
+    }
+}
+
+/// Series of the minimum acceptable return used by the Sortino and Omega ratios, which
+/// is the risk free series unless a constant one is set in the config.
+pub fn minimum_acceptable(
+    objective: &Objective,
+    risk_free: &TimeSeries,
+    dates: &[NaiveDate],
+    frequency: Frequency,
+) -> Result<TimeSeries> {
+    match objective.minimum_acceptable_return {
+        Some(r) => TimeSeries::constant(
+            "_minimum_acceptable".to_string(),
+            dates.to_vec(),
+            r,
+            frequency,
+        ),
+        None => Ok(risk_free.clone()),
+    }
+}
+
+pub fn objective_value(
+    metric: Metric,
+    p: &Portfolio,
+    risk_free: &TimeSeries,
+    minimum_acceptable: &TimeSeries,
+) -> Result<f64> {
+    match metric {
+        Metric::Sharpe => p.sharpe_ratio(risk_free),
+        Metric::Sortino => p.sortino_ratio(minimum_acceptable),
+        Metric::Calmar => Ok(p.calmar_ratio()),
+        Metric::Omega => p.omega_ratio(minimum_acceptable),
+    }
+}
+
+pub fn satisfies_max_cvar(risk: &Risk, p: &Portfolio) -> bool {
+    risk.max_cvar.is_none_or(|max_cvar| {
+        p.expected_shortfall(risk.max_cvar_confidence, risk.max_cvar_method) <= max_cvar
+    })
+}
+
+/// Split maximizing the objective among those satisfying the CVaR constraint, with
+/// both evaluated net of the costs of rebalancing back to the split at every period.
+///
+/// Metrics net of costs are not mean-variance, so the best split is searched among the
+/// max Sharpe ratio split and the `candidates`, keeping the former on ties. Other ties,
+/// such as infinite Calmar ratios when there is no drawdown, are broken by the Sharpe
+/// ratio. Undefined values, such as the Sharpe ratio of a split without excess returns
+/// or volatility, rank below every other one. None of them may satisfy the CVaR
+/// constraint.
+pub fn choose_split(
+    funds: &[TimeSeries],
+    risk_free: &TimeSeries,
+    minimum_acceptable: &TimeSeries,
+    config: &Config,
+    max_sharpe_split: &[f64],
+    candidates: &[Vec<f64>],
+) -> Result<Option<Vec<f64>>> {
+    let (objective, risk) = (&config.objective, &config.risk);
+
+    let worst_if_nan = |x: f64| if x.is_nan() { f64::NEG_INFINITY } else { x };
+
+    let mut best: Option<(&[f64], (f64, f64))> = None;
+
+    for split in std::iter::once(max_sharpe_split).chain(candidates.iter().map(|c| &c[..])) {
+        let p = Portfolio::with_policy(funds, split, RebalancePolicy::ConstantMix, &config.costs)?;
+
+        if !satisfies_max_cvar(risk, &p) {
+            continue;
+        }
+
+        let value = (
+            worst_if_nan(objective_value(
+                objective.metric,
+                &p,
+                risk_free,
+                minimum_acceptable,
+            )?),
+            worst_if_nan(p.sharpe_ratio(risk_free)?),
+        );
+
+        if best.is_none_or(|(_, best_value)| value > best_value) {
+            best = Some((split, value));
+        }
+    }
+
+    Ok(best.map(|(s, _)| s.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::monthly;
+
+    #[test]
+    fn undefined_objectives_rank_last() {
+        let mut config = crate::config::get_config();
+        config.objective.metric = Metric::Sharpe;
+        config.risk.max_cvar = None;
+
+        // The first fund has no excess returns nor volatility, so its Sharpe ratio is
+        // undefined.
+        let risk_free = monthly("cdi", vec![0.0; 4]);
+        let funds = vec![
+            monthly("a", vec![0.0; 4]),
+            monthly("b", vec![0.03, 0.0, 0.04, 0.01]),
+        ];
+        let choose = |candidates: &[Vec<f64>]| {
+            choose_split(
+                &funds,
+                &risk_free,
+                &risk_free,
+                &config,
+                &[1.0, 0.0],
+                candidates,
+            )
+            .unwrap()
+            .unwrap()
+        };
+
+        assert_eq!(choose(&[vec![0.0, 1.0]]), vec![0.0, 1.0]);
+        assert_eq!(choose(&[]), vec![1.0, 0.0]);
+    }
+}
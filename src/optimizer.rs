@@ -0,0 +1,329 @@
+use itertools::Itertools;
+
+const MAX_ITERATIONS: usize = 20_000;
+const TOLERANCE: f64 = 1e-12;
+
+/// Euclidean projection of `v` onto the simplex `{w: w >= 0, sum(w) = 1}`.
+pub fn project_onto_simplex(v: &[f64]) -> Vec<f64> {
+    let sorted = v
+        .iter()
+        .copied()
+        .sorted_by(|a, b| b.partial_cmp(a).expect("No NaNs should exist for ordering"))
+        .collect_vec();
+
+    let mut cumsum = 0.0;
+    let mut theta = 0.0;
+
+    for (i, x) in sorted.iter().enumerate() {
+        cumsum += x;
+        let t = (cumsum - 1.0) / (i + 1) as f64;
+
+        if x - t > 0.0 {
+            theta = t;
+        }
+    }
+
+    v.iter().map(|x| (x - theta).max(0.0)).collect()
+}
+
+fn mat_vec(matrix: &[Vec<f64>], v: &[f64]) -> Vec<f64> {
+    matrix
+        .iter()
+        .map(|row| row.iter().zip(v).map(|(a, b)| a * b).sum())
+        .collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn max_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y).abs())
+        .fold(0.0, f64::max)
+}
+
+pub fn variance(covariance: &[Vec<f64>], weights: &[f64]) -> f64 {
+    dot(weights, &mat_vec(covariance, weights))
+}
+
+pub fn mean(means: &[f64], weights: &[f64]) -> f64 {
+    dot(means, weights)
+}
+
+fn equal_weights(n: usize) -> Vec<f64> {
+    vec![1.0 / n as f64; n]
+}
+
+/// Minimizes `w'Σw - t * w'μ` over the simplex with accelerated projected gradient,
+/// starting from `start`. `t = 0` yields the minimum variance portfolio.
+pub fn mean_variance(
+    means: &[f64],
+    covariance: &[Vec<f64>],
+    tradeoff: f64,
+    start: &[f64],
+) -> Vec<f64> {
+    // Gershgorin bound on the largest eigenvalue of 2Σ.
+    let lipschitz = covariance
+        .iter()
+        .map(|row| 2.0 * row.iter().map(|x| x.abs()).sum::<f64>())
+        .fold(0.0, f64::max);
+    let step = if lipschitz > 0.0 {
+        1.0 / lipschitz
+    } else {
+        1.0
+    };
+
+    let mut w = project_onto_simplex(start);
+    let mut y = w.clone();
+    let mut momentum: f64 = 1.0;
+
+    for _ in 0..MAX_ITERATIONS {
+        let gradient = mat_vec(covariance, &y)
+            .iter()
+            .zip(means)
+            .map(|(s, m)| 2.0 * s - tradeoff * m)
+            .collect_vec();
+
+        let next = project_onto_simplex(
+            &y.iter()
+                .zip(&gradient)
+                .map(|(x, g)| x - step * g)
+                .collect_vec(),
+        );
+
+        let next_momentum = (1.0 + (1.0 + 4.0 * momentum * momentum).sqrt()) / 2.0;
+        let beta = (momentum - 1.0) / next_momentum;
+
+        y = next
+            .iter()
+            .zip(&w)
+            .map(|(n, p)| n + beta * (n - p))
+            .collect();
+
+        let converged = max_distance(&next, &w) < TOLERANCE;
+        w = next;
+        momentum = next_momentum;
+
+        if converged {
+            break;
+        }
+    }
+
+    w
+}
+
+pub fn min_variance(covariance: &[Vec<f64>]) -> Vec<f64> {
+    let n = covariance.len();
+
+    mean_variance(&vec![0.0; n], covariance, 0.0, &equal_weights(n))
+}
+
+fn sharpe(means: &[f64], covariance: &[Vec<f64>], weights: &[f64]) -> f64 {
+    mean(means, weights) / variance(covariance, weights).sqrt()
+}
+
+fn sharpe_gradient(means: &[f64], covariance: &[Vec<f64>], weights: &[f64]) -> Vec<f64> {
+    let sigma_w = mat_vec(covariance, weights);
+    let var = dot(weights, &sigma_w);
+    let std = var.sqrt();
+    let m = dot(means, weights);
+
+    means
+        .iter()
+        .zip(&sigma_w)
+        .map(|(mu, s)| mu / std - m * s / (var * std))
+        .collect()
+}
+
+fn ascend_sharpe(means: &[f64], covariance: &[Vec<f64>], start: Vec<f64>) -> Vec<f64> {
+    let mut w = start;
+    let mut value = sharpe(means, covariance, &w);
+    let mut step = 1.0;
+
+    for _ in 0..MAX_ITERATIONS {
+        let gradient = sharpe_gradient(means, covariance, &w);
+
+        if gradient.iter().any(|g| !g.is_finite()) {
+            break;
+        }
+
+        let scale = gradient.iter().map(|g| g.abs()).fold(0.0, f64::max);
+        if scale == 0.0 {
+            break;
+        }
+
+        // Backtracking line search on a step normalized by the gradient size.
+        let mut improved = None;
+        while step > 1e-14 {
+            let candidate = project_onto_simplex(
+                &w.iter()
+                    .zip(&gradient)
+                    .map(|(x, g)| x + step * g / scale)
+                    .collect_vec(),
+            );
+            let candidate_value = sharpe(means, covariance, &candidate);
+
+            if candidate_value.is_finite() && candidate_value > value {
+                improved = Some((candidate, candidate_value));
+                break;
+            }
+
+            step /= 2.0;
+        }
+
+        match improved {
+            Some((candidate, candidate_value)) => {
+                let converged = max_distance(&candidate, &w) < TOLERANCE;
+                w = candidate;
+                value = candidate_value;
+                step = (step * 2.0).min(1.0);
+
+                if converged {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
+
+    w
+}
+
+/// Portfolio with highest ratio of mean to standard deviation, where `means` and
+/// `covariance` are those of the excess returns over the risk free rate.
+///
+/// The problem is not convex, so the ascent is started from the equal split, the
+/// minimum variance portfolio and every single fund, keeping the best result.
+pub fn max_sharpe(means: &[f64], covariance: &[Vec<f64>]) -> Vec<f64> {
+    let n = means.len();
+
+    let mut starts = vec![equal_weights(n), min_variance(covariance)];
+    starts.extend((0..n).map(|i| {
+        let mut w = vec![0.0; n];
+        w[i] = 1.0;
+        w
+    }));
+
+    starts
+        .into_iter()
+        .map(|start| ascend_sharpe(means, covariance, start))
+        .map(|w| (sharpe(means, covariance, &w), w))
+        .filter(|(s, _)| !s.is_nan())
+        .max_by(|(s1, _), (s2, _)| s1.partial_cmp(s2).expect("NaNs were filtered out"))
+        .map(|(_, w)| w)
+        .unwrap_or_else(|| equal_weights(n))
+}
+
+/// Long-only mean-variance efficient portfolios, from the minimum variance portfolio up
+/// to the maximum mean one, with consecutive points at most `(max - min) / points`
+/// apart in mean.
+pub fn efficient_frontier(means: &[f64], covariance: &[Vec<f64>], points: usize) -> Vec<Vec<f64>> {
+    if means.is_empty() {
+        return Vec::new();
+    }
+
+    let start = min_variance(covariance);
+    let low = mean(means, &start);
+    let high = means.iter().copied().fold(f64::MIN, f64::max);
+
+    let gap = (high - low) / points.max(1) as f64;
+
+    let mut upper_tradeoff = 1.0;
+    let mut upper = mean_variance(means, covariance, upper_tradeoff, &start);
+    while mean(means, &upper) < high - gap / 2.0 && upper_tradeoff < 1e12 {
+        upper_tradeoff *= 2.0;
+        upper = mean_variance(means, covariance, upper_tradeoff, &upper);
+    }
+
+    let mut frontier = vec![(0.0, start)];
+    let mut pending = vec![(upper_tradeoff, upper)];
+
+    // Bisects on the trade-off until consecutive portfolios are close enough in mean.
+    while let Some((tradeoff, weights)) = pending.pop() {
+        let (last_tradeoff, last_weights) = frontier.last().expect("Frontier starts non-empty");
+
+        let too_far = mean(means, &weights) - mean(means, last_weights) > gap;
+        let splittable = tradeoff - last_tradeoff > 1e-9 * upper_tradeoff;
+
+        if too_far && splittable {
+            let middle_tradeoff = (tradeoff + last_tradeoff) / 2.0;
+            let middle = mean_variance(means, covariance, middle_tradeoff, last_weights);
+
+            pending.push((tradeoff, weights));
+            pending.push((middle_tradeoff, middle));
+        } else {
+            frontier.push((tradeoff, weights));
+        }
+    }
+
+    frontier.into_iter().map(|(_, w)| w).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &[f64], b: &[f64], tol: f64) {
+        assert!(max_distance(a, b) < tol, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn projection_onto_simplex() {
+        assert_close(
+            &project_onto_simplex(&[0.2, 0.3, 0.5]),
+            &[0.2, 0.3, 0.5],
+            1e-12,
+        );
+        assert_close(&project_onto_simplex(&[2.0, 0.0]), &[1.0, 0.0], 1e-12);
+        assert_close(
+            &project_onto_simplex(&[0.5, 0.5, 1.0]),
+            &[1.0 / 6.0, 1.0 / 6.0, 2.0 / 3.0],
+            1e-12,
+        );
+    }
+
+    #[test]
+    fn min_variance_of_uncorrelated_assets() {
+        // Weights should be proportional to the inverse of the variances.
+        let covariance = vec![vec![1.0, 0.0], vec![0.0, 4.0]];
+
+        assert_close(&min_variance(&covariance), &[0.8, 0.2], 1e-8);
+    }
+
+    #[test]
+    fn max_sharpe_of_uncorrelated_assets() {
+        // Unconstrained tangency portfolio is proportional to Σ^-1 μ = [0.1, 0.05].
+        let means = vec![0.1, 0.2];
+        let covariance = vec![vec![1.0, 0.0], vec![0.0, 4.0]];
+
+        assert_close(
+            &max_sharpe(&means, &covariance),
+            &[2.0 / 3.0, 1.0 / 3.0],
+            1e-6,
+        );
+    }
+
+    #[test]
+    fn frontier_goes_from_min_variance_to_max_mean() {
+        let means = vec![0.01, 0.02, 0.015];
+        let covariance = vec![
+            vec![0.01, 0.002, 0.0],
+            vec![0.002, 0.04, 0.001],
+            vec![0.0, 0.001, 0.02],
+        ];
+
+        let frontier = efficient_frontier(&means, &covariance, 20);
+
+        assert_close(&frontier[0], &min_variance(&covariance), 1e-9);
+        assert!((mean(&means, frontier.last().unwrap()) - 0.02).abs() < 1e-3);
+
+        for (a, b) in frontier.iter().tuple_windows() {
+            assert!(mean(&means, a) <= mean(&means, b) + 1e-12);
+            assert!(variance(&covariance, a) <= variance(&covariance, b) + 1e-12);
+        }
+
+        assert!(efficient_frontier(&[], &[], 20).is_empty());
+    }
+}
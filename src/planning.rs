@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    /// Every window of consecutive historical periods that fits in the history.
+    Historical,
+    /// Paths simulated with the Monte Carlo settings.
+    Simulated,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plan {
+    pub split: Vec<f64>,
+    /// Contribution at the start of every period after the first one needed to reach
+    /// the target with the desired confidence.
+    pub required_contribution: f64,
+    pub total_invested: f64,
+    pub median_value_at_end: f64,
+    pub probability_of_reaching_target: f64,
+}
+
+/// Every window of `horizon` consecutive periods of `returns`, which has none when
+/// `horizon` is longer than `returns`.
+pub fn historical_paths(returns: &[f64], horizon: usize) -> Vec<Vec<f64>> {
+    returns.windows(horizon).map(|w| w.to_vec()).collect()
+}
+
+/// Growth of the initial investment and of a contribution of 1 at the start of every
+/// period after the first, so that the value at the end is linear on the contribution.
+fn growth(returns: &[f64]) -> (f64, f64) {
+    let mut initial_growth = 1.0;
+    let mut contributions_growth = 0.0;
+
+    for (t, r) in returns.iter().enumerate() {
+        if t > 0 {
+            contributions_growth += 1.0;
+        }
+
+        initial_growth *= 1.0 + r;
+        contributions_growth *= 1.0 + r;
+    }
+
+    (initial_growth, contributions_growth)
+}
+
+pub fn value_at_end(returns: &[f64], initial: f64, contribution: f64) -> f64 {
+    let (initial_growth, contributions_growth) = growth(returns);
+
+    initial * initial_growth + contribution * contributions_growth
+}
+
+fn sorted(mut values: Vec<f64>) -> Vec<f64> {
+    values.sort_by(|a, b| a.partial_cmp(b).expect("No NaNs should exist for ordering"));
+    values
+}
+
+/// Smallest contribution such that at least a `confidence` fraction of `paths` reach
+/// `target`. Paths that reach it without contributions require none.
+pub fn required_contribution(
+    paths: &[Vec<f64>],
+    initial: f64,
+    target: f64,
+    confidence: f64,
+) -> f64 {
+    let required = sorted(
+        paths
+            .iter()
+            .map(|p| {
+                let (initial_growth, contributions_growth) = growth(p);
+                let missing = target - initial * initial_growth;
+
+                if missing <= 0.0 {
+                    0.0
+                } else if contributions_growth > 0.0 {
+                    missing / contributions_growth
+                } else {
+                    f64::INFINITY
+                }
+            })
+            .collect(),
+    );
+
+    if required.is_empty() {
+        return f64::INFINITY;
+    }
+
+    let k = ((confidence * required.len() as f64).ceil() as usize).clamp(1, required.len());
+
+    required[k - 1]
+}
+
+pub fn plan(
+    paths: &[Vec<f64>],
+    split: Vec<f64>,
+    initial: f64,
+    target: f64,
+    confidence: f64,
+) -> Plan {
+    let contribution = required_contribution(paths, initial, target, confidence);
+    let values = sorted(
+        paths
+            .iter()
+            .map(|p| value_at_end(p, initial, contribution))
+            .collect(),
+    );
+    let horizon = paths.first().map_or(0, |p| p.len());
+
+    Plan {
+        split,
+        required_contribution: contribution,
+        total_invested: initial + contribution * horizon.saturating_sub(1) as f64,
+        median_value_at_end: values.get(values.len() / 2).copied().unwrap_or(initial),
+        probability_of_reaching_target: values.iter().filter(|v| **v >= target - 1e-9).count()
+            as f64
+            / values.len().max(1) as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contribution_for_constant_returns() {
+        // With no returns, the target is reached by contributing the missing amount over
+        // the remaining periods.
+        let paths = historical_paths(&[0.0; 12], 11);
+
+        let plan = plan(&paths, vec![1.0], 1000.0, 2000.0, 0.9);
+
+        assert!((plan.required_contribution - 100.0).abs() < 1e-9);
+        assert_eq!(plan.probability_of_reaching_target, 1.0);
+    }
+
+    #[test]
+    fn windows_fit_in_history() {
+        let returns = [0.01, 0.02, 0.03, 0.04, 0.05];
+
+        assert_eq!(
+            historical_paths(&returns, 3),
+            vec![
+                vec![0.01, 0.02, 0.03],
+                vec![0.02, 0.03, 0.04],
+                vec![0.03, 0.04, 0.05]
+            ]
+        );
+        assert!(historical_paths(&returns, 6).is_empty());
+    }
+
+    #[test]
+    fn contribution_grows_with_confidence() {
+        let returns = [0.05, -0.02, 0.01, 0.03, -0.04].repeat(3);
+        let paths = historical_paths(&returns, 12);
+
+        let low = required_contribution(&paths, 1000.0, 5000.0, 0.5);
+        let high = required_contribution(&paths, 1000.0, 5000.0, 1.0);
+
+        assert!(low < high);
+        assert!(paths
+            .iter()
+            .all(|p| value_at_end(p, 1000.0, high) >= 5000.0 - 1e-9));
+    }
+
+    #[test]
+    fn no_contribution_when_initial_suffices() {
+        let paths = historical_paths(&[0.01; 12], 12);
+
+        assert_eq!(required_contribution(&paths, 1000.0, 1000.0, 0.95), 0.0);
+    }
+}
@@ -1,47 +1,265 @@
+use std::path::Path;
+
 use anyhow::{anyhow, Result};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use statrs::statistics::Statistics;
 
+use crate::calendar::{Calendar, BUSINESS_DAYS_PER_YEAR};
+use crate::cashflow::{project, CashFlows, Projection};
+use crate::cnpj::Cnpj;
+use crate::costs::Costs;
+use crate::id::SeriesId;
+use crate::optimizer::{mean, variance};
+use crate::risk::{expected_shortfall, risk_report, value_at_risk, RiskMethod, RiskReport};
+use crate::simulation::{simulate, RebalancePolicy};
+use crate::tax::{after_tax, FundClass, Taxes};
+
 #[derive(Serialize, Deserialize)]
 pub struct AllTimeSeries {
     pub timeseries: Vec<TimeSeries>,
 }
 
+impl AllTimeSeries {
+    /// Series written as JSON to `path`, such as the models and benchmarks of the
+    /// timeseries step.
+    pub fn load(path: &Path) -> Result<AllTimeSeries> {
+        let timeseries = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Could not read {}: {}", path.display(), e))?;
+
+        Ok(serde_json::from_str(&timeseries)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    #[default]
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl Frequency {
+    pub fn periods_per_year(&self) -> f64 {
+        match self {
+            Frequency::Daily => BUSINESS_DAYS_PER_YEAR,
+            Frequency::Weekly => 52.0,
+            Frequency::Monthly => 12.0,
+            Frequency::Quarterly => 4.0,
+            Frequency::Yearly => 1.0,
+        }
+    }
+
+    /// Start of the period containing `date`: the day itself, the Monday of its week or
+    /// the first day of its month, quarter or year.
+    pub fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        let month_start = |month| {
+            NaiveDate::from_ymd_opt(date.year(), month, 1)
+                .expect("First day of the month is always valid")
+        };
+
+        match self {
+            Frequency::Daily => date,
+            Frequency::Weekly => date - Days::new(date.weekday().num_days_from_monday() as u64),
+            Frequency::Monthly => month_start(date.month()),
+            Frequency::Quarterly => month_start(3 * ((date.month() - 1) / 3) + 1),
+            Frequency::Yearly => month_start(1),
+        }
+    }
+
+    /// Start of the period following the one starting at `start`.
+    pub fn next_period(&self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Frequency::Daily => start + Days::new(1),
+            Frequency::Weekly => start + Days::new(7),
+            Frequency::Monthly => start + Months::new(1),
+            Frequency::Quarterly => start + Months::new(3),
+            Frequency::Yearly => start + Months::new(12),
+        }
+    }
+}
+
+impl std::fmt::Display for Frequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Frequency::Daily => write!(f, "daily"),
+            Frequency::Weekly => write!(f, "weekly"),
+            Frequency::Monthly => write!(f, "monthly"),
+            Frequency::Quarterly => write!(f, "quarterly"),
+            Frequency::Yearly => write!(f, "yearly"),
+        }
+    }
+}
+
+impl std::str::FromStr for Frequency {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Frequency> {
+        match s {
+            "daily" => Ok(Frequency::Daily),
+            "weekly" => Ok(Frequency::Weekly),
+            "monthly" => Ok(Frequency::Monthly),
+            "quarterly" => Ok(Frequency::Quarterly),
+            "yearly" => Ok(Frequency::Yearly),
+            _ => Err(anyhow!("Can't parse frequency '{}'", s)),
+        }
+    }
+}
+
+/// Compounds the total `growth` over `periods` periods into a yearly rate.
+pub(crate) fn annualize_growth(growth: f64, periods: usize, frequency: Frequency) -> f64 {
+    growth.powf(frequency.periods_per_year() / periods as f64) - 1.0
+}
+
+/// Periods spanned by the returns at `dates`. A daily return accrues every business day
+/// since the previous quota, so daily returns span the business days from the first of
+/// them to the last one.
+fn periods_spanned(dates: &[NaiveDate], frequency: Frequency) -> usize {
+    match (frequency, dates.first(), dates.last()) {
+        (Frequency::Daily, Some(first), Some(last)) => {
+            Calendar::anbima().business_days_between(*first, *last) as usize + 1
+        }
+        _ => dates.len(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Drawdown {
+    pub max_drawdown: f64,
+    pub trough: Option<NaiveDate>,
+    pub longest_duration: usize,
+    pub time_to_recovery: Option<usize>,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct TimeSeries {
-    pub id: String,
+    pub id: SeriesId,
+    pub dates: Vec<NaiveDate>,
+    #[serde(default)]
+    pub frequency: Frequency,
     multipliers: Vec<f64>,
     pub returns: Vec<f64>,
 }
 
 impl TimeSeries {
-    pub fn new(id: String, returns: Vec<f64>) -> TimeSeries {
+    pub fn new(
+        id: impl Into<SeriesId>,
+        dates: Vec<NaiveDate>,
+        returns: Vec<f64>,
+    ) -> Result<TimeSeries> {
+        let id = id.into();
+        if dates.len() != returns.len() {
+            return Err(anyhow!(
+                "Series '{}' has {} dates but {} returns",
+                id,
+                dates.len(),
+                returns.len()
+            ));
+        }
+
+        if let Some((d1, d2)) = dates.iter().tuple_windows().find(|(d1, d2)| d1 >= d2) {
+            return Err(anyhow!(
+                "Dates of series '{}' should be strictly increasing, but {} comes before {}",
+                id,
+                d1,
+                d2
+            ));
+        }
+
         let multipliers = returns.iter().map(|x| 1.0 + x).collect();
 
-        TimeSeries {
+        Ok(TimeSeries {
             id,
+            dates,
+            frequency: Frequency::default(),
             multipliers,
             returns,
+        })
+    }
+
+    /// CNPJ of the fund of the series, or none for benchmarks and other series.
+    pub fn cnpj(&self) -> Option<Cnpj> {
+        self.id.cnpj()
+    }
+
+    /// Series with the same `return_per_period` at each of `dates`.
+    pub fn constant(
+        id: impl Into<SeriesId>,
+        dates: Vec<NaiveDate>,
+        return_per_period: f64,
+        frequency: Frequency,
+    ) -> Result<TimeSeries> {
+        let returns = vec![return_per_period; dates.len()];
+
+        Ok(TimeSeries::new(id, dates, returns)?.with_frequency(frequency))
+    }
+
+    pub fn with_frequency(mut self, frequency: Frequency) -> TimeSeries {
+        self.frequency = frequency;
+        self
+    }
+
+    /// Returns of this series at each of `dates`, failing with the list of periods
+    /// that are absent from it.
+    pub fn returns_at(&self, dates: &[NaiveDate]) -> Result<Vec<f64>> {
+        let mut returns = Vec::with_capacity(dates.len());
+        let mut missing = Vec::new();
+
+        for date in dates {
+            match self.dates.binary_search(date) {
+                Ok(i) => returns.push(self.returns[i]),
+                Err(_) => missing.push(date.to_string()),
+            }
+        }
+
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "Series '{}' has no values for periods: {}",
+                self.id,
+                missing.join(", ")
+            ));
         }
+
+        Ok(returns)
     }
 
-    pub fn subtract(&self, other: &TimeSeries) -> TimeSeries {
+    pub fn slice(&self, from_date: NaiveDate, to_date: NaiveDate) -> Result<TimeSeries> {
+        let start = self.dates.partition_point(|d| *d < from_date);
+        let end = self.dates.partition_point(|d| *d <= to_date).max(start);
+
+        Ok(TimeSeries::new(
+            self.id.clone(),
+            self.dates[start..end].to_vec(),
+            self.returns[start..end].to_vec(),
+        )?
+        .with_frequency(self.frequency))
+    }
+
+    pub fn subtract(&self, other: &TimeSeries) -> Result<TimeSeries> {
+        if self.frequency != other.frequency {
+            return Err(anyhow!(
+                "Can't subtract '{}' from '{}' since they have different frequencies",
+                other.id,
+                self.id
+            ));
+        }
+
+        let other_returns = other.returns_at(&self.dates)?;
+
         let returns = self
             .returns
             .iter()
-            .zip(&other.returns)
+            .zip(&other_returns)
             .map(|(x, y)| x - y)
             .collect::<Vec<_>>();
 
-        let id = self.id.clone() + "_" + other.id.as_str();
-
-        let multipliers = returns.iter().map(|x| 1.0 + x).collect();
+        let id = format!("{}_{}", self.id, other.id);
 
-        TimeSeries {
-            id,
-            multipliers,
-            returns,
-        }
+        Ok(TimeSeries::new(id, self.dates.clone(), returns)?.with_frequency(self.frequency))
     }
 
     pub fn average_returns(&self) -> f64 {
@@ -55,6 +273,213 @@ impl TimeSeries {
     pub fn calculate_value_at_end(&self, initial_investment: f64) -> f64 {
         initial_investment * self.multipliers.iter().product::<f64>()
     }
+
+    /// Geometric average return per year.
+    pub fn annualized_returns(&self) -> f64 {
+        annualize_growth(
+            self.calculate_value_at_end(1.0),
+            periods_spanned(&self.dates, self.frequency),
+            self.frequency,
+        )
+    }
+
+    pub fn annualized_volatility(&self) -> f64 {
+        self.std_returns() * self.frequency.periods_per_year().sqrt()
+    }
+
+    /// Value along the periods with the deposits and withdrawals of `cash_flows`,
+    /// along with the money-weighted and time-weighted returns.
+    pub fn project_cash_flows(&self, cash_flows: &CashFlows) -> Result<Projection> {
+        project(self, cash_flows)
+    }
+
+    /// Value at the end of each period of an initial investment.
+    pub fn equity_curve(&self, initial_investment: f64) -> Vec<f64> {
+        self.multipliers
+            .iter()
+            .scan(initial_investment, |value, multiplier| {
+                *value *= multiplier;
+                Some(*value)
+            })
+            .collect()
+    }
+
+    /// Relative distance of the equity curve to its running maximum at the end of each
+    /// period, counting the initial investment as a peak. Zero means a new peak.
+    pub fn underwater(&self) -> Vec<f64> {
+        self.equity_curve(1.0)
+            .iter()
+            .scan(1.0f64, |peak, value| {
+                *peak = peak.max(*value);
+                Some(value / *peak - 1.0)
+            })
+            .collect()
+    }
+
+    pub fn max_drawdown(&self) -> f64 {
+        self.underwater().into_iter().fold(0.0, f64::min).abs()
+    }
+
+    /// Maximum drawdown along with its trough, the longest stretch of consecutive
+    /// periods under water and the number of periods it took to recover from the
+    /// maximum drawdown, if it did.
+    pub fn drawdown(&self) -> Drawdown {
+        let underwater = self.underwater();
+
+        let trough = underwater
+            .iter()
+            .enumerate()
+            .filter(|(_, x)| **x < 0.0)
+            .min_by(|(_, x), (_, y)| x.partial_cmp(y).expect("No NaNs should exist for ordering"))
+            .map(|(i, _)| i);
+
+        let time_to_recovery =
+            trough.and_then(|trough| underwater[trough..].iter().position(|x| *x >= 0.0));
+
+        let longest_duration = underwater
+            .iter()
+            .scan(0, |duration, x| {
+                *duration = if *x < 0.0 { *duration + 1 } else { 0 };
+                Some(*duration)
+            })
+            .max()
+            .unwrap_or(0);
+
+        Drawdown {
+            max_drawdown: trough.map_or(0.0, |i| -underwater[i]),
+            trough: trough.map(|i| self.dates[i]),
+            longest_duration,
+            time_to_recovery,
+        }
+    }
+}
+
+/// Union of the periods of all `series`. Fails listing the gaps of every series that
+/// does not cover all of them, so that series are never combined by position alone.
+pub fn align(series: &[TimeSeries]) -> Result<Vec<NaiveDate>> {
+    if !series.iter().map(|ts| ts.frequency).all_equal() {
+        return Err(anyhow!(
+            "Series with different frequencies can't be combined"
+        ));
+    }
+
+    let dates = series
+        .iter()
+        .flat_map(|ts| ts.dates.iter().copied())
+        .sorted()
+        .dedup()
+        .collect_vec();
+
+    let gaps = series
+        .iter()
+        .filter_map(|ts| ts.returns_at(&dates).err())
+        .map(|e| e.to_string())
+        .collect_vec();
+
+    if !gaps.is_empty() {
+        return Err(anyhow!("Series are not aligned. {}", gaps.join(". ")));
+    }
+
+    Ok(dates)
+}
+
+/// Sample covariance matrix of the returns of `series`, aligned on their periods.
+pub fn covariance_matrix(series: &[TimeSeries]) -> Result<Vec<Vec<f64>>> {
+    let dates = align(series)?;
+    let n = dates.len() as f64;
+
+    let centered = series
+        .iter()
+        .map(|ts| {
+            let average = ts.average_returns();
+            ts.returns.iter().map(|x| x - average).collect_vec()
+        })
+        .collect_vec();
+
+    Ok(centered
+        .iter()
+        .map(|x| {
+            centered
+                .iter()
+                .map(|y| x.iter().zip(y).map(|(a, b)| a * b).sum::<f64>() / (n - 1.0))
+                .collect()
+        })
+        .collect())
+}
+
+/// Moments of a set of funds, so that statistics of a portfolio of them can be computed
+/// for any split without building its time series.
+pub struct PortfolioStatistics {
+    pub ids: Vec<SeriesId>,
+    pub dates: Vec<NaiveDate>,
+    pub frequency: Frequency,
+    pub means: Vec<f64>,
+    pub covariance: Vec<Vec<f64>>,
+    pub excess_means: Vec<f64>,
+    pub excess_covariance: Vec<Vec<f64>>,
+    returns: Vec<Vec<f64>>,
+}
+
+impl PortfolioStatistics {
+    pub fn new(funds: &[TimeSeries], risk_free: &TimeSeries) -> Result<PortfolioStatistics> {
+        let dates = align(funds)?;
+
+        let excess = funds
+            .iter()
+            .map(|f| f.subtract(risk_free))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(PortfolioStatistics {
+            ids: funds.iter().map(|f| f.id.clone()).collect(),
+            dates,
+            frequency: funds.first().map(|f| f.frequency).unwrap_or_default(),
+            means: funds.iter().map(|f| f.average_returns()).collect(),
+            covariance: covariance_matrix(funds)?,
+            excess_means: excess.iter().map(|f| f.average_returns()).collect(),
+            excess_covariance: covariance_matrix(&excess)?,
+            returns: funds.iter().map(|f| f.returns.clone()).collect(),
+        })
+    }
+
+    pub fn average(&self, split: &[f64]) -> f64 {
+        mean(&self.means, split)
+    }
+
+    pub fn std(&self, split: &[f64]) -> f64 {
+        variance(&self.covariance, split).sqrt()
+    }
+
+    pub fn sharpe_ratio(&self, split: &[f64]) -> f64 {
+        mean(&self.excess_means, split) / variance(&self.excess_covariance, split).sqrt()
+    }
+
+    pub fn calculate_value_at_end(&self, split: &[f64], initial_investment: f64) -> f64 {
+        (0..self.dates.len()).fold(initial_investment, |acc, i| {
+            acc * (1.0
+                + self
+                    .returns
+                    .iter()
+                    .zip(split)
+                    .map(|(r, w)| w * r[i])
+                    .sum::<f64>())
+        })
+    }
+
+    pub fn annualized_returns(&self, split: &[f64]) -> f64 {
+        annualize_growth(
+            self.calculate_value_at_end(split, 1.0),
+            periods_spanned(&self.dates, self.frequency),
+            self.frequency,
+        )
+    }
+
+    pub fn annualized_volatility(&self, split: &[f64]) -> f64 {
+        self.std(split) * self.frequency.periods_per_year().sqrt()
+    }
+
+    pub fn annualized_sharpe_ratio(&self, split: &[f64]) -> f64 {
+        self.sharpe_ratio(split) * self.frequency.periods_per_year().sqrt()
+    }
 }
 
 pub struct Portfolio {
@@ -67,31 +492,75 @@ impl Portfolio {
             return Err(anyhow!("'ts' and 'split' have different lengths"));
         }
 
-        if !(split.iter().sum::<f64>() == 1.0) {
+        if (split.iter().sum::<f64>() - 1.0).abs() > 1e-9 {
             return Err(anyhow!("Split does not sum to 1"));
         }
 
-        let returns =
-            ts.iter()
-                .zip(split)
-                .fold(vec![0.0; ts[0].returns.len()], |mut acc, (ts, split)| {
-                    for (i, multiplier) in ts.returns.iter().enumerate() {
-                        acc[i] += split * multiplier
-                    }
-                    acc
-                });
-
-        let id = ts
+        let dates = align(ts)?;
+
+        let returns = ts
             .iter()
-            .map(|x| x.id.clone())
-            .collect::<Vec<_>>()
-            .join("_");
+            .zip(split)
+            .fold(vec![0.0; dates.len()], |mut acc, (ts, split)| {
+                for (i, multiplier) in ts.returns.iter().enumerate() {
+                    acc[i] += split * multiplier
+                }
+                acc
+            });
 
-        let final_ts = TimeSeries::new(id, returns);
+        let id = ts.iter().map(|x| x.id.to_string()).join("_");
+
+        let final_ts = TimeSeries::new(id, dates, returns)?
+            .with_frequency(ts.first().map(|t| t.frequency).unwrap_or_default());
 
         Ok(Portfolio { final_ts })
     }
 
+    /// Portfolio whose weights start at `split` and are rebalanced according to
+    /// `policy`, instead of being kept at `split` at every period as in `new`, with
+    /// returns net of `costs`.
+    pub fn with_policy(
+        ts: &[TimeSeries],
+        split: &[f64],
+        policy: RebalancePolicy,
+        costs: &Costs,
+    ) -> Result<Portfolio> {
+        if (split.iter().sum::<f64>() - 1.0).abs() > 1e-9 {
+            return Err(anyhow!("Split does not sum to 1"));
+        }
+
+        let id = ts.iter().map(|x| x.id.to_string()).join("_");
+
+        let final_ts = simulate(ts, split, policy, costs)?.timeseries(id)?;
+
+        Ok(Portfolio { final_ts })
+    }
+
+    /// Portfolio kept at `split` in which each fund is bought at the start and redeemed
+    /// at the end, with the returns of each fund net of its taxes. The exempt part of the
+    /// returns of real estate funds is taken from the series in `dividends` with their id.
+    pub fn after_tax(
+        ts: &[TimeSeries],
+        split: &[f64],
+        taxes: &Taxes,
+        dividends: &[TimeSeries],
+    ) -> Result<Portfolio> {
+        let net = ts
+            .iter()
+            .map(|t| {
+                let class = taxes.class(&t.id);
+                let fund_dividends = match class {
+                    FundClass::RealEstate => dividends.iter().find(|d| d.id == t.id),
+                    _ => None,
+                };
+
+                after_tax(t, class, fund_dividends)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Portfolio::new(&net, split)
+    }
+
     pub fn std(&self) -> f64 {
         self.final_ts.std_returns()
     }
@@ -104,20 +573,110 @@ impl Portfolio {
         self.final_ts.calculate_value_at_end(initial_investment)
     }
 
-    pub fn sharpe_ratio(&self, risk_free: &TimeSeries) -> f64 {
-        let excess = self.final_ts.subtract(risk_free);
+    pub fn project_cash_flows(&self, cash_flows: &CashFlows) -> Result<Projection> {
+        self.final_ts.project_cash_flows(cash_flows)
+    }
+
+    pub fn sharpe_ratio(&self, risk_free: &TimeSeries) -> Result<f64> {
+        let excess = self.final_ts.subtract(risk_free)?;
+
+        Ok(excess.average_returns() / excess.std_returns())
+    }
+
+    pub fn dates(&self) -> &[NaiveDate] {
+        &self.final_ts.dates
+    }
+
+    pub fn returns(&self) -> &[f64] {
+        &self.final_ts.returns
+    }
+
+    pub fn annualized_returns(&self) -> f64 {
+        self.final_ts.annualized_returns()
+    }
+
+    pub fn annualized_volatility(&self) -> f64 {
+        self.final_ts.annualized_volatility()
+    }
 
-        excess.average_returns() / excess.std_returns()
+    pub fn annualized_sharpe_ratio(&self, risk_free: &TimeSeries) -> Result<f64> {
+        Ok(self.sharpe_ratio(risk_free)? * self.final_ts.frequency.periods_per_year().sqrt())
+    }
+
+    /// Average excess return over `minimum_acceptable` divided by the downside
+    /// deviation, which only penalizes periods below it.
+    pub fn sortino_ratio(&self, minimum_acceptable: &TimeSeries) -> Result<f64> {
+        let excess = self.final_ts.subtract(minimum_acceptable)?;
+
+        let downside = excess
+            .returns
+            .iter()
+            .map(|x| x.min(0.0).powi(2))
+            .sum::<f64>()
+            / excess.returns.len() as f64;
+
+        Ok(excess.average_returns() / downside.sqrt())
+    }
+
+    pub fn calmar_ratio(&self) -> f64 {
+        self.annualized_returns() / self.max_drawdown()
+    }
+
+    /// Ratio between the gains above `threshold` and the losses below it.
+    pub fn omega_ratio(&self, threshold: &TimeSeries) -> Result<f64> {
+        let excess = self.final_ts.subtract(threshold)?;
+
+        let gains = excess.returns.iter().map(|x| x.max(0.0)).sum::<f64>();
+        let losses = excess.returns.iter().map(|x| (-x).max(0.0)).sum::<f64>();
+
+        Ok(gains / losses)
+    }
+
+    pub fn value_at_risk(&self, confidence: f64, method: RiskMethod) -> f64 {
+        value_at_risk(&self.final_ts.returns, confidence, method)
+    }
+
+    pub fn expected_shortfall(&self, confidence: f64, method: RiskMethod) -> f64 {
+        expected_shortfall(&self.final_ts.returns, confidence, method)
+    }
+
+    /// Value at risk and expected shortfall with every method, for each confidence level.
+    pub fn risk_reports(&self, confidence_levels: &[f64]) -> Vec<RiskReport> {
+        confidence_levels
+            .iter()
+            .flat_map(|c| {
+                RiskMethod::ALL
+                    .iter()
+                    .map(|m| risk_report(&self.final_ts.returns, *c, *m))
+            })
+            .collect()
+    }
+
+    pub fn equity_curve(&self, initial_investment: f64) -> Vec<f64> {
+        self.final_ts.equity_curve(initial_investment)
+    }
+
+    pub fn underwater(&self) -> Vec<f64> {
+        self.final_ts.underwater()
+    }
+
+    pub fn max_drawdown(&self) -> f64 {
+        self.final_ts.max_drawdown()
+    }
+
+    pub fn drawdown(&self) -> Drawdown {
+        self.final_ts.drawdown()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::months;
 
     #[test]
     fn calculate_final_value() {
-        let ts = TimeSeries::new("".to_string(), vec![0.05, 0.07, 0.03]);
+        let ts = TimeSeries::new("".to_string(), months(3), vec![0.05, 0.07, 0.03]).unwrap();
 
         assert_eq!(
             ts.calculate_value_at_end(1.0),
@@ -126,4 +685,157 @@ mod tests {
 
         assert_eq!(ts.average_returns(), 0.05);
     }
+
+    #[test]
+    fn annualized_statistics() {
+        let ts = TimeSeries::new("".to_string(), months(12), vec![0.01; 12]).unwrap();
+
+        assert!((ts.annualized_returns() - (1.01f64.powi(12) - 1.0)).abs() < 1e-12);
+        assert!(ts.annualized_volatility().abs() < 1e-12);
+
+        let ts = ts.with_frequency(Frequency::Yearly);
+
+        assert!((ts.annualized_returns() - 0.01).abs() < 1e-12);
+
+        // Without a quota on Wednesday 2024-02-14, the return of Thursday spans two days
+        // after the Carnival, so three returns accrue four business days.
+        let dates = [9, 15, 16]
+            .iter()
+            .map(|day| NaiveDate::from_ymd_opt(2024, 2, *day).unwrap())
+            .collect();
+        let ts = TimeSeries::new("".to_string(), dates, vec![0.01, 0.02, 0.01])
+            .unwrap()
+            .with_frequency(Frequency::Daily);
+        let growth: f64 = 1.01 * 1.02 * 1.01;
+
+        assert!((ts.annualized_returns() - (growth.powf(252.0 / 4.0) - 1.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn drawdown_statistics() {
+        let ts = TimeSeries::new(
+            "".to_string(),
+            months(6),
+            vec![0.1, -0.1, -0.1, 0.1, 0.2, -0.05],
+        )
+        .unwrap();
+
+        let underwater = ts.underwater();
+
+        assert_eq!(underwater[0], 0.0);
+        assert!((underwater[2] - (0.81 - 1.0)).abs() < 1e-12);
+
+        let drawdown = ts.drawdown();
+
+        assert!((drawdown.max_drawdown - 0.19).abs() < 1e-12);
+        assert_eq!(drawdown.trough, Some(months(3)[2]));
+        assert_eq!(drawdown.time_to_recovery, Some(2));
+        assert_eq!(drawdown.longest_duration, 3);
+    }
+
+    #[test]
+    fn downside_ratios() {
+        let ts =
+            TimeSeries::new("".to_string(), months(4), vec![0.03, -0.01, 0.02, -0.02]).unwrap();
+        let p = Portfolio::new(&[ts], &[1.0]).unwrap();
+
+        let mar =
+            TimeSeries::constant("mar".to_string(), months(4), 0.0, Frequency::Monthly).unwrap();
+
+        let downside = ((0.01f64.powi(2) + 0.02f64.powi(2)) / 4.0).sqrt();
+        assert!((p.sortino_ratio(&mar).unwrap() - 0.005 / downside).abs() < 1e-12);
+
+        assert!((p.omega_ratio(&mar).unwrap() - 0.05 / 0.03).abs() < 1e-12);
+
+        // Worth 1.01929212 after 4 months, so 5.9% a year, with a 2% drawdown in the
+        // last month.
+        assert!((p.max_drawdown() - 0.02).abs() < 1e-12);
+        assert!((p.calmar_ratio() - (1.01929212f64.powi(3) - 1.0) / 0.02).abs() < 1e-9);
+        assert!((p.calmar_ratio() - 2.95).abs() < 1e-4);
+    }
+
+    #[test]
+    fn real_estate_dividends_after_tax() {
+        let fii =
+            TimeSeries::new("11.111.111/0001-91".to_string(), months(3), vec![0.01; 3]).unwrap();
+        let dividends = TimeSeries::new(fii.id.clone(), months(3), vec![0.008; 3]).unwrap();
+        let taxes = Taxes {
+            default_class: FundClass::RealEstate,
+            funds: Vec::new(),
+        };
+
+        let with_dividends =
+            Portfolio::after_tax(std::slice::from_ref(&fii), &[1.0], &taxes, &[dividends]).unwrap();
+        let without_dividends = Portfolio::after_tax(&[fii], &[1.0], &taxes, &[]).unwrap();
+
+        // Only the capital gain of 0.2% a month pays 20%.
+        let gain = 1.002f64.powi(3) - 1.0;
+        let expected = 1.01f64.powi(3) - 0.2 * gain;
+
+        assert!((with_dividends.calculate_value_at_end(1.0) - expected).abs() < 1e-12);
+        assert!(
+            with_dividends.calculate_value_at_end(1.0)
+                > without_dividends.calculate_value_at_end(1.0)
+        );
+    }
+
+    #[test]
+    fn subtract_aligns_on_dates() {
+        let ts =
+            TimeSeries::new("a".to_string(), months(3)[1..].to_vec(), vec![0.05, 0.07]).unwrap();
+        let rf = TimeSeries::new("rf".to_string(), months(3), vec![0.01, 0.02, 0.03]).unwrap();
+
+        let excess = ts.subtract(&rf).unwrap();
+
+        assert_eq!(excess.dates, months(3)[1..]);
+        assert!((excess.returns[0] - 0.03).abs() < 1e-12);
+        assert!((excess.returns[1] - 0.04).abs() < 1e-12);
+
+        assert!(rf.subtract(&ts).is_err());
+    }
+
+    #[test]
+    fn covariance_matches_portfolio_variance() {
+        let a = TimeSeries::new("a".to_string(), months(4), vec![0.01, 0.03, -0.02, 0.05]).unwrap();
+        let b = TimeSeries::new("b".to_string(), months(4), vec![0.02, -0.01, 0.01, 0.0]).unwrap();
+
+        let cov = covariance_matrix(&[a.clone(), b.clone()]).unwrap();
+        let p = Portfolio::new(&[a, b], &[0.3, 0.7]).unwrap();
+
+        let var = 0.09 * cov[0][0] + 2.0 * 0.21 * cov[0][1] + 0.49 * cov[1][1];
+
+        assert!((var.sqrt() - p.std()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn statistics_match_portfolio() {
+        let a = TimeSeries::new("a".to_string(), months(4), vec![0.01, 0.03, -0.02, 0.05]).unwrap();
+        let b = TimeSeries::new("b".to_string(), months(4), vec![0.02, -0.01, 0.01, 0.0]).unwrap();
+        let rf =
+            TimeSeries::new("rf".to_string(), months(4), vec![0.001, 0.002, 0.0, 0.003]).unwrap();
+
+        let funds = [a, b];
+        let split = [0.3, 0.7];
+
+        let stats = PortfolioStatistics::new(&funds, &rf).unwrap();
+        let p = Portfolio::new(&funds, &split).unwrap();
+
+        assert!((stats.average(&split) - p.average()).abs() < 1e-12);
+        assert!((stats.std(&split) - p.std()).abs() < 1e-12);
+        assert!((stats.sharpe_ratio(&split) - p.sharpe_ratio(&rf).unwrap()).abs() < 1e-12);
+        assert!(
+            (stats.calculate_value_at_end(&split, 1.0) - p.calculate_value_at_end(1.0)).abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn portfolio_reports_gaps() {
+        let a = TimeSeries::new("a".to_string(), months(3), vec![0.01, 0.02, 0.03]).unwrap();
+        let b = TimeSeries::new("b".to_string(), months(2), vec![0.01, 0.02]).unwrap();
+
+        let err = Portfolio::new(&[a, b], &[0.5, 0.5]).err().unwrap();
+
+        assert!(err.to_string().contains("2021-03-01"));
+    }
 }
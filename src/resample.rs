@@ -0,0 +1,145 @@
+use anyhow::{anyhow, Result};
+use chrono::{Days, NaiveDate};
+
+use crate::calendar::Calendar;
+use crate::portfolio::{Frequency, TimeSeries};
+
+/// Whether the returns of `ts` cover the whole period of `frequency` starting at `start`.
+/// Daily returns only need to cover its business days.
+fn covers(ts: &TimeSeries, start: NaiveDate, frequency: Frequency, calendar: &Calendar) -> bool {
+    let (Some(first), Some(last)) = (ts.dates.first(), ts.dates.last()) else {
+        return false;
+    };
+    let end = frequency.next_period(start);
+
+    match ts.frequency {
+        Frequency::Daily => {
+            *first <= calendar.following(start) && *last >= calendar.preceding(end - Days::new(1))
+        }
+        source => *first <= start && source.next_period(*last) >= end,
+    }
+}
+
+/// Compounds the returns of `ts` into periods of `frequency`, dated at their start. The
+/// first and last periods are left out unless `ts` covers them entirely, so that no
+/// partial period is mistaken for a whole one.
+pub fn resample(ts: &TimeSeries, frequency: Frequency, calendar: &Calendar) -> Result<TimeSeries> {
+    if ts.frequency == frequency {
+        return Ok(ts.clone());
+    }
+
+    if frequency.periods_per_year() > ts.frequency.periods_per_year() {
+        return Err(anyhow!(
+            "Can't resample the {} returns of '{}' into {} ones",
+            ts.frequency,
+            ts.id,
+            frequency
+        ));
+    }
+
+    let mut starts: Vec<NaiveDate> = Vec::new();
+    let mut growths: Vec<f64> = Vec::new();
+
+    for (date, r) in ts.dates.iter().zip(&ts.returns) {
+        let start = frequency.period_start(*date);
+
+        if starts.last() != Some(&start) {
+            starts.push(start);
+            growths.push(1.0);
+        }
+
+        *growths.last_mut().expect("A period was just pushed") *= 1.0 + r;
+    }
+
+    let (dates, returns) = starts
+        .into_iter()
+        .zip(growths)
+        .filter(|(start, _)| covers(ts, *start, frequency, calendar))
+        .map(|(start, growth)| (start, growth - 1.0))
+        .unzip();
+
+    Ok(TimeSeries::new(ts.id.clone(), dates, returns)?.with_frequency(frequency))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    /// A return of 0.1% on every business day of `calendar` from `from` to `to`.
+    fn daily_on(calendar: &Calendar, from: NaiveDate, to: NaiveDate) -> TimeSeries {
+        let dates = from
+            .iter_days()
+            .take_while(|d| *d <= to)
+            .filter(|d| calendar.is_business_day(*d))
+            .collect::<Vec<_>>();
+        let returns = vec![0.001; dates.len()];
+
+        TimeSeries::new("a".to_string(), dates, returns)
+            .unwrap()
+            .with_frequency(Frequency::Daily)
+    }
+
+    /// A return of 0.1% on every weekday from `from` to `to`.
+    fn daily(from: NaiveDate, to: NaiveDate) -> TimeSeries {
+        daily_on(&Calendar::weekdays(), from, to)
+    }
+
+    #[test]
+    fn compounds_whole_months_of_daily_returns() {
+        // Starts after the first business day of January and ends on the last one of
+        // March, which is a Friday.
+        let ts = daily(date(2023, 1, 3), date(2023, 3, 31));
+
+        let monthly = resample(&ts, Frequency::Monthly, &Calendar::weekdays()).unwrap();
+
+        assert_eq!(monthly.dates, vec![date(2023, 2, 1), date(2023, 3, 1)]);
+        assert!((monthly.returns[0] - (1.001_f64.powi(20) - 1.0)).abs() < 1e-12);
+        assert!((monthly.returns[1] - (1.001_f64.powi(23) - 1.0)).abs() < 1e-12);
+        assert_eq!(monthly.frequency, Frequency::Monthly);
+    }
+
+    #[test]
+    fn holidays_do_not_leave_periods_uncovered() {
+        // 2024-01-01 is a Monday and a holiday, and 2024-03-29 is Good Friday.
+        let calendar = Calendar::anbima();
+        let ts = daily_on(&calendar, date(2024, 1, 2), date(2024, 3, 28));
+
+        let monthly = resample(&ts, Frequency::Monthly, &calendar).unwrap();
+        assert_eq!(monthly.dates.len(), 3);
+        assert!((monthly.returns[1] - (1.001_f64.powi(19) - 1.0)).abs() < 1e-12);
+
+        let monthly = resample(&ts, Frequency::Monthly, &Calendar::weekdays()).unwrap();
+        assert_eq!(monthly.dates, vec![date(2024, 2, 1)]);
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        // From a Wednesday to a Friday.
+        let ts = daily(date(2023, 3, 1), date(2023, 3, 17));
+
+        let weekly = resample(&ts, Frequency::Weekly, &Calendar::weekdays()).unwrap();
+
+        assert_eq!(weekly.dates, vec![date(2023, 3, 6), date(2023, 3, 13)]);
+        assert!((weekly.returns[0] - (1.001_f64.powi(5) - 1.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn compounds_whole_quarters_of_monthly_returns() {
+        let dates = (2..=12).map(|m| date(2022, m, 1)).collect();
+        let ts = TimeSeries::new("a".to_string(), dates, vec![0.01; 11]).unwrap();
+
+        let quarterly = resample(&ts, Frequency::Quarterly, &Calendar::weekdays()).unwrap();
+
+        assert_eq!(
+            quarterly.dates,
+            vec![date(2022, 4, 1), date(2022, 7, 1), date(2022, 10, 1)]
+        );
+        assert!((quarterly.returns[0] - (1.01_f64.powi(3) - 1.0)).abs() < 1e-12);
+
+        assert!(resample(&ts, Frequency::Daily, &Calendar::weekdays()).is_err());
+    }
+}
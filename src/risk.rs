@@ -0,0 +1,171 @@
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use statrs::distribution::{Continuous, ContinuousCDF, Normal};
+use statrs::statistics::Statistics;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskMethod {
+    Historical,
+    Gaussian,
+    CornishFisher,
+}
+
+impl RiskMethod {
+    pub const ALL: [RiskMethod; 3] = [
+        RiskMethod::Historical,
+        RiskMethod::Gaussian,
+        RiskMethod::CornishFisher,
+    ];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskReport {
+    pub method: RiskMethod,
+    pub confidence: f64,
+    pub value_at_risk: f64,
+    pub expected_shortfall: f64,
+}
+
+fn standard_normal() -> Normal {
+    Normal::new(0.0, 1.0).expect("Standard normal parameters are valid")
+}
+
+/// Skewness and excess kurtosis of `returns`.
+fn higher_moments(returns: &[f64]) -> (f64, f64) {
+    let n = returns.len() as f64;
+    let mean = returns.iter().mean();
+
+    let moment = |k| returns.iter().map(|x| (x - mean).powi(k)).sum::<f64>() / n;
+    let m2 = moment(2);
+
+    if m2 == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    (moment(3) / m2.powf(1.5), moment(4) / (m2 * m2) - 3.0)
+}
+
+/// Quantile of the standard normal adjusted for skewness and excess kurtosis.
+fn cornish_fisher(z: f64, skewness: f64, kurtosis: f64) -> f64 {
+    z + (z * z - 1.0) * skewness / 6.0 + (z.powi(3) - 3.0 * z) * kurtosis / 24.0
+        - (2.0 * z.powi(3) - 5.0 * z) * skewness * skewness / 36.0
+}
+
+fn worst_returns(returns: &[f64], confidence: f64) -> Vec<f64> {
+    let n = returns.len();
+    // Tolerance so that e.g. 5% of 20 returns is exactly one return.
+    let k = ((n as f64 * (1.0 - confidence) - 1e-9).ceil() as usize).clamp(1, n.max(1));
+
+    returns
+        .iter()
+        .copied()
+        .sorted_by(|a, b| a.partial_cmp(b).expect("No NaNs should exist for ordering"))
+        .take(k)
+        .collect()
+}
+
+/// Loss, as a positive fraction, that is not exceeded in a period with probability
+/// `confidence`.
+pub fn value_at_risk(returns: &[f64], confidence: f64, method: RiskMethod) -> f64 {
+    let alpha = 1.0 - confidence;
+
+    match method {
+        RiskMethod::Historical => -worst_returns(returns, confidence)
+            .last()
+            .copied()
+            .unwrap_or(f64::NAN),
+        RiskMethod::Gaussian => {
+            let z = standard_normal().inverse_cdf(alpha);
+
+            -(returns.iter().mean() + returns.iter().std_dev() * z)
+        }
+        RiskMethod::CornishFisher => {
+            let (skewness, kurtosis) = higher_moments(returns);
+            let z = cornish_fisher(standard_normal().inverse_cdf(alpha), skewness, kurtosis);
+
+            -(returns.iter().mean() + returns.iter().std_dev() * z)
+        }
+    }
+}
+
+/// Average loss, as a positive fraction, in the periods beyond the value at risk.
+pub fn expected_shortfall(returns: &[f64], confidence: f64, method: RiskMethod) -> f64 {
+    let alpha = 1.0 - confidence;
+    let normal = standard_normal();
+
+    match method {
+        RiskMethod::Historical => -worst_returns(returns, confidence).iter().mean(),
+        RiskMethod::Gaussian => {
+            let z = normal.inverse_cdf(alpha);
+
+            -(returns.iter().mean() - returns.iter().std_dev() * normal.pdf(z) / alpha)
+        }
+        RiskMethod::CornishFisher => {
+            // Averages the adjusted quantiles over the tail with the midpoint rule.
+            const STEPS: usize = 1000;
+            let (skewness, kurtosis) = higher_moments(returns);
+
+            let tail = (0..STEPS)
+                .map(|i| {
+                    let u = alpha * (i as f64 + 0.5) / STEPS as f64;
+                    cornish_fisher(normal.inverse_cdf(u), skewness, kurtosis)
+                })
+                .sum::<f64>()
+                / STEPS as f64;
+
+            -(returns.iter().mean() + returns.iter().std_dev() * tail)
+        }
+    }
+}
+
+pub fn risk_report(returns: &[f64], confidence: f64, method: RiskMethod) -> RiskReport {
+    RiskReport {
+        method,
+        confidence,
+        value_at_risk: value_at_risk(returns, confidence, method),
+        expected_shortfall: expected_shortfall(returns, confidence, method),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn historical() {
+        let returns = (1..=20).map(|i| i as f64 / 100.0 - 0.05).collect_vec();
+
+        // The 5% worst of 20 returns is the single worst one, and 10% the two worst.
+        assert!((value_at_risk(&returns, 0.95, RiskMethod::Historical) - 0.04).abs() < 1e-12);
+        assert!((value_at_risk(&returns, 0.9, RiskMethod::Historical) - 0.03).abs() < 1e-12);
+        assert!((expected_shortfall(&returns, 0.9, RiskMethod::Historical) - 0.035).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cornish_fisher_matches_gaussian_without_skew_or_kurtosis() {
+        let z = standard_normal().inverse_cdf(0.05);
+
+        assert_eq!(cornish_fisher(z, 0.0, 0.0), z);
+    }
+
+    #[test]
+    fn gaussian_shortfall_exceeds_value_at_risk() {
+        let returns = [0.01, -0.02, 0.03, 0.0, -0.01, 0.02];
+
+        for method in RiskMethod::ALL {
+            let var = value_at_risk(&returns, 0.95, method);
+            let es = expected_shortfall(&returns, 0.95, method);
+
+            assert!(es >= var, "{:?}: {} < {}", method, es, var);
+        }
+
+        let mean = returns.iter().mean();
+        let std = returns.iter().std_dev();
+
+        assert!(
+            (value_at_risk(&returns, 0.95, RiskMethod::Gaussian) - (1.6448536 * std - mean)).abs()
+                < 1e-6
+        );
+    }
+}
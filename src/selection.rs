@@ -0,0 +1,216 @@
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::config::FundsFilters;
+use crate::id::SeriesId;
+use crate::portfolio::TimeSeries;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum DropReason {
+    NotIncluded,
+    Excluded,
+    /// Fewer than two returns in the window, so its volatility can't be computed.
+    TooFewReturns {
+        returns: usize,
+    },
+    Volatility {
+        volatility: f64,
+        threshold: f64,
+    },
+    Ranking {
+        rank: usize,
+        number_of_funds: usize,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DroppedFund {
+    pub id: SeriesId,
+    #[serde(flatten)]
+    pub reason: DropReason,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Selection {
+    pub selected: Vec<TimeSeries>,
+    pub dropped: Vec<DroppedFund>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SelectionReport {
+    pub selected: Vec<SeriesId>,
+    pub dropped: Vec<DroppedFund>,
+}
+
+impl Selection {
+    pub fn report(&self) -> SelectionReport {
+        SelectionReport {
+            selected: self.selected.iter().map(|ts| ts.id.clone()).collect(),
+            dropped: self.dropped.clone(),
+        }
+    }
+}
+
+/// Applies the include list, then the exclude list and finally the volatility threshold,
+/// with the volatility of each fund computed only over `from_date..=to_date`.
+pub fn apply_filters(
+    funds: Vec<TimeSeries>,
+    filters: &FundsFilters,
+    from_date: NaiveDate,
+    to_date: NaiveDate,
+) -> Result<Selection> {
+    let mut selected = Vec::new();
+    let mut dropped = Vec::new();
+
+    for fund in funds {
+        let drop = |reason| DroppedFund {
+            id: fund.id.clone(),
+            reason,
+        };
+
+        let cnpj = fund.cnpj();
+
+        if !filters.include.is_empty() && !cnpj.is_some_and(|c| filters.include.contains(&c)) {
+            dropped.push(drop(DropReason::NotIncluded));
+            continue;
+        }
+
+        if cnpj.is_some_and(|c| filters.exclude.contains(&c)) {
+            dropped.push(drop(DropReason::Excluded));
+            continue;
+        }
+
+        let window = fund.slice(from_date, to_date)?;
+
+        if window.returns.len() < 2 {
+            dropped.push(drop(DropReason::TooFewReturns {
+                returns: window.returns.len(),
+            }));
+            continue;
+        }
+
+        let volatility = window.std_returns();
+
+        if volatility > filters.volatility_threshold {
+            dropped.push(drop(DropReason::Volatility {
+                volatility,
+                threshold: filters.volatility_threshold,
+            }));
+            continue;
+        }
+
+        selected.push(fund);
+    }
+
+    Ok(Selection { selected, dropped })
+}
+
+/// Keeps the `number_of_funds` funds with highest average returns among the selected ones.
+pub fn keep_best(selection: Selection, number_of_funds: usize) -> Selection {
+    let Selection {
+        selected: mut funds,
+        mut dropped,
+    } = selection;
+
+    funds.sort_by(|t1, t2| {
+        t1.average_returns()
+            .partial_cmp(&t2.average_returns())
+            .expect("No NaNs should exist for ordering.")
+    });
+    funds.reverse();
+
+    let n = number_of_funds.min(funds.len());
+
+    for (i, fund) in funds.iter().enumerate().skip(n) {
+        dropped.push(DroppedFund {
+            id: fund.id.clone(),
+            reason: DropReason::Ranking {
+                rank: i + 1,
+                number_of_funds,
+            },
+        });
+    }
+    funds.truncate(n);
+
+    Selection {
+        selected: funds,
+        dropped,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::monthly;
+
+    #[test]
+    fn filters_are_applied_in_order() {
+        let funds = vec![
+            monthly("11.111.111/0001-91", vec![0.01, 0.02, 0.01]),
+            monthly("22.222.222/0001-91", vec![0.01, 0.02, 0.01]),
+            monthly("33.333.333/0001-91", vec![0.2, -0.2, 0.2]),
+            monthly("44.444.444/0001-91", vec![0.01, 0.02, 0.01]),
+        ];
+        let filters = FundsFilters {
+            include: vec![
+                "11111111000191".parse().unwrap(),
+                "22.222.222/0001-91".parse().unwrap(),
+                "33.333.333/0001-91".parse().unwrap(),
+            ],
+            exclude: vec!["22.222.222/0001-91".parse().unwrap()],
+            volatility_threshold: 0.05,
+        };
+
+        let from = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2021, 3, 1).unwrap();
+
+        let selection = apply_filters(funds, &filters, from, to).unwrap();
+
+        assert_eq!(
+            selection.report().selected,
+            vec![SeriesId::from("11.111.111/0001-91")]
+        );
+
+        let reasons = selection
+            .dropped
+            .iter()
+            .map(|d| (d.id.clone(), &d.reason))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            reasons[0],
+            ("22.222.222/0001-91".into(), &DropReason::Excluded)
+        );
+        assert!(matches!(reasons[1].1, DropReason::Volatility { .. }));
+        assert_eq!(
+            reasons[2],
+            ("44.444.444/0001-91".into(), &DropReason::NotIncluded)
+        );
+    }
+
+    #[test]
+    fn funds_without_enough_returns_in_the_window_are_dropped() {
+        let funds = vec![
+            monthly("11.111.111/0001-91", vec![0.01, 0.02, 0.01]),
+            monthly("22.222.222/0001-91", vec![0.01]),
+        ];
+        let filters = FundsFilters {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            volatility_threshold: 0.05,
+        };
+
+        let from = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2021, 3, 1).unwrap();
+
+        let selection = keep_best(apply_filters(funds, &filters, from, to).unwrap(), 2);
+
+        assert_eq!(selection.selected.len(), 1);
+        assert_eq!(
+            selection.dropped[0].reason,
+            DropReason::TooFewReturns { returns: 1 }
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::costs::{Costs, Holdings};
+use crate::portfolio::{align, Frequency, TimeSeries};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RebalancePolicy {
+    /// Back to the target weights at every period.
+    ConstantMix,
+    /// Never rebalances, so weights drift with the returns of each fund.
+    BuyAndHold,
+    /// Back to the target weights whenever any weight drifts more than `threshold`
+    /// from its target.
+    Band { threshold: f64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Simulation {
+    pub policy: RebalancePolicy,
+    pub frequency: Frequency,
+    pub dates: Vec<NaiveDate>,
+    /// Returns net of costs.
+    pub returns: Vec<f64>,
+    /// Returns before costs.
+    pub gross_returns: Vec<f64>,
+    /// Weights at the start of each period.
+    pub weights: Vec<Vec<f64>>,
+    /// Periods at whose start weights were brought back to the target.
+    pub rebalances: Vec<NaiveDate>,
+}
+
+impl Simulation {
+    pub fn timeseries(&self, id: String) -> Result<TimeSeries> {
+        Ok(
+            TimeSeries::new(id, self.dates.clone(), self.returns.clone())?
+                .with_frequency(self.frequency),
+        )
+    }
+
+    pub fn gross_timeseries(&self, id: String) -> Result<TimeSeries> {
+        Ok(
+            TimeSeries::new(id, self.dates.clone(), self.gross_returns.clone())?
+                .with_frequency(self.frequency),
+        )
+    }
+}
+
+/// Applies one period of `returns` to `weights`, updating them to their drifted values
+/// and returning the return of the portfolio.
+pub fn drift(weights: &mut [f64], returns: &[f64]) -> f64 {
+    let r = weights.iter().zip(returns).map(|(w, r)| w * r).sum::<f64>();
+
+    for (w, fund_return) in weights.iter_mut().zip(returns) {
+        *w *= (1.0 + fund_return) / (1.0 + r);
+    }
+
+    r
+}
+
+fn needs_rebalance(policy: RebalancePolicy, weights: &[f64], target: &[f64]) -> bool {
+    match policy {
+        RebalancePolicy::ConstantMix => true,
+        RebalancePolicy::BuyAndHold => false,
+        RebalancePolicy::Band { threshold } => weights
+            .iter()
+            .zip(target)
+            .any(|(w, t)| (w - t).abs() > threshold),
+    }
+}
+
+/// Path of an allocation of `split` on `ts` that is rebalanced according to `policy`,
+/// paying `costs` on every trade, including the purchases at the start and the
+/// redemption of everything at the end.
+pub fn simulate(
+    ts: &[TimeSeries],
+    split: &[f64],
+    policy: RebalancePolicy,
+    costs: &Costs,
+) -> Result<Simulation> {
+    if ts.len() != split.len() {
+        return Err(anyhow!("'ts' and 'split' have different lengths"));
+    }
+
+    let dates = align(ts)?;
+    let frequency = ts.first().map(|t| t.frequency).unwrap_or_default();
+
+    let mut holdings = Holdings::new(ts.iter().map(|t| t.id.clone()).collect());
+    holdings.rebalance(split, costs.initial_investment, 0, costs);
+
+    let mut value = costs.initial_investment;
+    let mut path = Vec::with_capacity(dates.len());
+    let mut returns = Vec::with_capacity(dates.len());
+    let mut gross_returns = Vec::with_capacity(dates.len());
+    let mut rebalances = Vec::new();
+
+    for (i, date) in dates.iter().enumerate() {
+        if i > 0
+            && needs_rebalance(policy, &holdings.weights(), split)
+            && holdings.rebalance(split, 0.0, i, costs)
+        {
+            rebalances.push(*date);
+        }
+
+        let mut weights = holdings.weights();
+        path.push(weights.clone());
+
+        let period_returns = ts.iter().map(|t| t.returns[i]).collect::<Vec<_>>();
+        gross_returns.push(drift(&mut weights, &period_returns));
+        holdings.apply_returns(&period_returns);
+
+        let value_at_end = if i + 1 == dates.len() {
+            holdings.liquidate(dates.len(), costs)
+        } else {
+            holdings.value()
+        };
+
+        returns.push(value_at_end / value - 1.0);
+        value = value_at_end;
+    }
+
+    Ok(Simulation {
+        policy,
+        frequency,
+        dates,
+        returns,
+        gross_returns,
+        weights: path,
+        rebalances,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portfolio::Portfolio;
+
+    fn funds() -> Vec<TimeSeries> {
+        let dates = (1..=4)
+            .map(|m| NaiveDate::from_ymd_opt(2021, m, 1).unwrap())
+            .collect::<Vec<_>>();
+
+        vec![
+            TimeSeries::new("a".to_string(), dates.clone(), vec![0.1, 0.1, -0.05, 0.2]).unwrap(),
+            TimeSeries::new("b".to_string(), dates, vec![0.0, 0.01, 0.02, -0.01]).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn constant_mix_matches_portfolio() {
+        let funds = funds();
+        let split = [0.4, 0.6];
+
+        let simulation = simulate(
+            &funds,
+            &split,
+            RebalancePolicy::ConstantMix,
+            &Costs::default(),
+        )
+        .unwrap();
+        let p = Portfolio::new(&funds, &split).unwrap();
+
+        let ts = simulation.timeseries("".to_string()).unwrap();
+
+        assert!((ts.calculate_value_at_end(1.0) - p.calculate_value_at_end(1.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn buy_and_hold_drifts() {
+        let funds = funds();
+        let split = [0.4, 0.6];
+
+        let simulation = simulate(
+            &funds,
+            &split,
+            RebalancePolicy::BuyAndHold,
+            &Costs::default(),
+        )
+        .unwrap();
+        let ts = simulation.timeseries("".to_string()).unwrap();
+
+        let expected =
+            0.4 * funds[0].calculate_value_at_end(1.0) + 0.6 * funds[1].calculate_value_at_end(1.0);
+
+        assert!((ts.calculate_value_at_end(1.0) - expected).abs() < 1e-12);
+        assert!(simulation.rebalances.is_empty());
+        assert!((simulation.weights[1][0] - 0.44 / 1.04).abs() < 1e-12);
+    }
+
+    #[test]
+    fn band_rebalances_only_beyond_threshold() {
+        let funds = funds();
+        let split = [0.4, 0.6];
+
+        // Weight of 'a' is 0.423 after the first period and 0.445 after the second.
+        let simulation = simulate(
+            &funds,
+            &split,
+            RebalancePolicy::Band { threshold: 0.03 },
+            &Costs::default(),
+        )
+        .unwrap();
+
+        assert_eq!(simulation.rebalances, vec![funds[0].dates[2]]);
+        assert!((simulation.weights[2][0] - split[0]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn costs_are_subtracted_from_returns() {
+        let funds = funds();
+        let split = [0.4, 0.6];
+        let costs = Costs {
+            proportional: 0.01,
+            ..Costs::default()
+        };
+
+        let simulation = simulate(&funds, &split, RebalancePolicy::BuyAndHold, &costs).unwrap();
+        let net = simulation.timeseries("".to_string()).unwrap();
+        let gross = simulation.gross_timeseries("".to_string()).unwrap();
+
+        // Pays 1% when buying and 1% when redeeming everything at the end.
+        let expected = 0.99 * 0.99 * gross.calculate_value_at_end(1.0);
+
+        assert!((net.calculate_value_at_end(1.0) - expected).abs() < 1e-12);
+    }
+}
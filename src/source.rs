@@ -0,0 +1,742 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use polars::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cnpj::Cnpj;
+use crate::cvm::{daily_returns, monthly_returns, quotas, read_inf_diario, Quota};
+use crate::fii::{read_inf_mensal, reports, FiiReport};
+use crate::id::SeriesId;
+use crate::portfolio::Frequency;
+
+/// Return of series `id` over the period starting at `date`, or over the day `date` for
+/// daily returns, or none if it is missing from the raw file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub id: SeriesId,
+    pub date: NaiveDate,
+    pub value: Option<f64>,
+}
+
+/// Raw data in some layout, normalized into records.
+pub trait Source {
+    /// Records of all the files matched by the source, which may depend on each other,
+    /// e.g. when returns span two files.
+    fn records(&self, paths: &[PathBuf]) -> Result<Vec<Record>>;
+
+    fn frequency(&self) -> Frequency;
+
+    /// Records along with the reports of FIIs and the rows skipped in the files, reading
+    /// them only once. Only sources of FII reports have reports, and only CVM sources
+    /// skip rows.
+    fn read(&self, paths: &[PathBuf]) -> Result<SourceData> {
+        Ok(SourceData {
+            records: self.records(paths)?,
+            ..SourceData::default()
+        })
+    }
+}
+
+/// Everything read from the files of a source.
+#[derive(Debug, Clone, Default)]
+pub struct SourceData {
+    pub records: Vec<Record>,
+    pub fii_reports: Vec<FiiReport>,
+    pub skipped: Vec<SkippedRow>,
+}
+
+/// Row of a raw file skipped for its malformed CNPJ, at `line` of the file counting the
+/// header as the first one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SkippedRow {
+    pub file: String,
+    pub line: usize,
+    pub cnpj: String,
+}
+
+impl SkippedRow {
+    /// Rows skipped from the file at `path`, given by their index in its data frame.
+    fn from_file(path: &Path, rows: Vec<(usize, String)>) -> Vec<SkippedRow> {
+        rows.into_iter()
+            .map(|(i, cnpj)| SkippedRow {
+                file: path.display().to_string(),
+                line: i + 2,
+                cnpj,
+            })
+            .collect()
+    }
+}
+
+/// Series of the pipeline fed by a source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Series {
+    Funds,
+    /// Risk-free rates, inflation and indices, identified by the id of their records.
+    Benchmarks,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum Format {
+    /// Tables copied from the broker, named `{CNPJ}_{YEAR}.csv`.
+    Broker,
+    /// Tables copied from <https://brasilindicadores.com.br>, with a row per year.
+    BrasilIndicadores,
+    /// Daily closing levels of an index, such as the IFIX, Ibovespa or IMA-B, into daily
+    /// or monthly returns.
+    IndexLevels {
+        #[serde(default)]
+        frequency: Frequency,
+    },
+    /// Daily reports of the CVM open data into daily or monthly returns, keeping only
+    /// `funds` unless empty.
+    CvmDaily {
+        #[serde(default)]
+        funds: Vec<Cnpj>,
+        #[serde(default)]
+        frequency: Frequency,
+    },
+    /// Monthly reports of FIIs of the CVM open data, keeping only `funds` unless empty.
+    CvmFii {
+        #[serde(default)]
+        funds: Vec<Cnpj>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawSource {
+    /// Directory with the files, or a glob pattern matching them.
+    pub path: String,
+    pub series: Series,
+    #[serde(flatten)]
+    pub format: Format,
+}
+
+impl Format {
+    pub fn source(&self) -> Box<dyn Source> {
+        match self {
+            Format::Broker => Box::new(Broker),
+            Format::BrasilIndicadores => Box::new(BrasilIndicadores),
+            Format::IndexLevels { frequency } => Box::new(IndexLevels {
+                frequency: *frequency,
+            }),
+            Format::CvmDaily { funds, frequency } => Box::new(CvmDaily {
+                funds: funds.clone(),
+                frequency: *frequency,
+            }),
+            Format::CvmFii { funds } => Box::new(CvmFii {
+                funds: funds.clone(),
+            }),
+        }
+    }
+}
+
+impl RawSource {
+    /// Files matched by the source, sorted. A missing directory matches none.
+    pub fn files(&self) -> Result<Vec<PathBuf>> {
+        let path = Path::new(&self.path);
+
+        let files = if path.is_dir() {
+            path.read_dir()?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<Result<BTreeSet<_>>>()?
+        } else {
+            glob::glob(&self.path)?.collect::<Result<BTreeSet<_>, _>>()?
+        };
+
+        Ok(files.into_iter().filter(|p| p.is_file()).collect())
+    }
+}
+
+/// Records read by the source at `path`, with returns at `frequency`.
+#[derive(Debug, Clone)]
+pub struct SourceRecords {
+    pub path: String,
+    pub frequency: Frequency,
+    pub records: Vec<Record>,
+}
+
+/// Fails when two sources give a return of the same series at the same date, or give
+/// returns of the same series at different frequencies, since they would be mixed into
+/// a single series.
+pub fn check_overlaps(sources: &[SourceRecords]) -> Result<()> {
+    let mut frequencies: BTreeMap<&SeriesId, &SourceRecords> = BTreeMap::new();
+    let mut dates: BTreeMap<(&SeriesId, NaiveDate), &str> = BTreeMap::new();
+
+    for source in sources {
+        for record in &source.records {
+            let first = *frequencies.entry(&record.id).or_insert(source);
+
+            if first.frequency != source.frequency {
+                return Err(anyhow!(
+                    "Series '{}' has {} returns in the source '{}' but {} ones in the source '{}'",
+                    record.id,
+                    first.frequency,
+                    first.path,
+                    source.frequency,
+                    source.path
+                ));
+            }
+
+            if let Some(path) = dates.insert((&record.id, record.date), &source.path) {
+                return Err(anyhow!(
+                    "Series '{}' has a return at {} in both the source '{}' and the source '{}'",
+                    record.id,
+                    record.date,
+                    path,
+                    source.path
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn month_number(s: &str) -> Result<u32> {
+    match s {
+        "Jan" => Ok(1),
+        "Fev" => Ok(2),
+        "Mar" => Ok(3),
+        "Abr" => Ok(4),
+        "Mai" => Ok(5),
+        "Jun" => Ok(6),
+        "Jul" => Ok(7),
+        "Ago" => Ok(8),
+        "Set" => Ok(9),
+        "Out" => Ok(10),
+        "Nov" => Ok(11),
+        "Dez" => Ok(12),
+        _ => Err(anyhow!("Can't parse month '{}'", s)),
+    }
+}
+
+/// Number with dots separating thousands and a decimal comma, or none for placeholders
+/// such as `---`.
+pub fn parse_decimal(s: &str) -> Option<f64> {
+    s.trim()
+        .replace('.', "")
+        .replace(',', ".")
+        .parse::<f64>()
+        .ok()
+}
+
+/// Percentage with a decimal comma as a fraction, or none for placeholders such as `---`.
+pub fn parse_percentage(s: &str) -> Option<f64> {
+    parse_decimal(s).map(|x| x / 100.0)
+}
+
+fn file_stem(path: &Path) -> Result<&str> {
+    path.file_stem()
+        .ok_or(anyhow!("File name not found"))?
+        .to_str()
+        .ok_or(anyhow!("Invalid UTF8 for file name"))
+}
+
+fn file_name(path: &Path) -> Result<&str> {
+    path.file_name()
+        .ok_or(anyhow!("File name not found"))?
+        .to_str()
+        .ok_or(anyhow!("Invalid UTF8 for file name"))
+}
+
+/// Returns between consecutive quotas, or levels, of each series at `frequency`.
+fn quota_returns(quotas: &[Quota], frequency: Frequency) -> Result<Vec<Record>> {
+    match frequency {
+        Frequency::Daily => Ok(daily_returns(quotas)
+            .into_iter()
+            .map(|r| Record {
+                id: r.id,
+                date: r.date,
+                value: Some(r.value),
+            })
+            .collect()),
+        Frequency::Monthly => Ok(monthly_returns(quotas)
+            .into_iter()
+            .map(|r| Record {
+                id: r.id,
+                date: r.month,
+                value: Some(r.value),
+            })
+            .collect()),
+        _ => Err(anyhow!(
+            "Returns from quotas can only be daily or monthly, but {} was requested",
+            frequency
+        )),
+    }
+}
+
+pub(crate) fn read_table(path: &Path) -> Result<DataFrame> {
+    Ok(CsvReader::from_path(path)?
+        .has_header(true)
+        .infer_schema(Some(0))
+        .finish()?)
+}
+
+/// Records of row `row` of a table with a column per month, named in Portuguese, of
+/// `year`. Other columns, such as the accumulated return, are ignored.
+fn monthly_row(df: &DataFrame, row: usize, id: &SeriesId, year: i32) -> Result<Vec<Record>> {
+    let mut records = Vec::new();
+
+    for column in df.get_columns() {
+        let Ok(month) = month_number(column.name()) else {
+            continue;
+        };
+
+        let value = column.str()?.get(row).and_then(parse_percentage);
+
+        records.push(Record {
+            id: id.clone(),
+            date: NaiveDate::from_ymd_opt(year, month, 1).ok_or(anyhow!(
+                "Invalid year {} for series '{}'",
+                year,
+                id
+            ))?,
+            value,
+        });
+    }
+
+    Ok(records)
+}
+
+/// Tables copied from the broker with the monthly returns of a fund in a single year.
+pub struct Broker;
+
+impl Source for Broker {
+    fn records(&self, paths: &[PathBuf]) -> Result<Vec<Record>> {
+        let mut records = Vec::new();
+
+        for path in paths {
+            let (cnpj, year) = file_stem(path)?.rsplit_once('_').ok_or(anyhow!(
+                "Invalid file name '{}'. Couldn't split CNPJ and year.",
+                path.display()
+            ))?;
+            let cnpj: Cnpj = cnpj
+                .parse()
+                .map_err(|e| anyhow!("Invalid file name '{}': {}", path.display(), e))?;
+            let year = year
+                .parse()
+                .map_err(|_| anyhow!("Invalid year in file name '{}'", path.display()))?;
+
+            let df = read_table(path)?;
+
+            if df.height() == 0 {
+                return Err(anyhow!("No returns in '{}'", path.display()));
+            }
+
+            records.extend(monthly_row(&df, 0, &SeriesId::Fund(cnpj), year)?);
+        }
+
+        Ok(records)
+    }
+
+    fn frequency(&self) -> Frequency {
+        Frequency::Monthly
+    }
+}
+
+/// Tables copied from brasilindicadores with a row per year, identified by the file name.
+pub struct BrasilIndicadores;
+
+impl Source for BrasilIndicadores {
+    fn records(&self, paths: &[PathBuf]) -> Result<Vec<Record>> {
+        let mut records = Vec::new();
+
+        for path in paths {
+            let id = SeriesId::Named(file_stem(path)?.to_string());
+            let df = read_table(path)?;
+            let years = df.column("Ano/Mês")?.str()?;
+
+            for (row, year) in years.into_iter().enumerate() {
+                let Some(year) = year else {
+                    continue;
+                };
+                let year = year
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid year '{}' in '{}'", year, path.display()))?;
+
+                records.extend(monthly_row(&df, row, &id, year)?);
+            }
+        }
+
+        Ok(records)
+    }
+
+    fn frequency(&self) -> Frequency {
+        Frequency::Monthly
+    }
+}
+
+/// Daily closing levels of an index with `Data` (`DD/MM/YYYY`) and `Fechamento` columns,
+/// identified by the file name. Returns are taken between levels as for the quotas of
+/// the CVM daily reports.
+pub struct IndexLevels {
+    pub frequency: Frequency,
+}
+
+impl Source for IndexLevels {
+    fn records(&self, paths: &[PathBuf]) -> Result<Vec<Record>> {
+        let mut levels = Vec::new();
+
+        for path in paths {
+            let id = SeriesId::Named(file_stem(path)?.to_string());
+            let df = read_table(path)?;
+
+            let dates = df.column("Data")?.str()?;
+            let closes = df.column("Fechamento")?.str()?;
+
+            for (date, close) in dates.into_iter().zip(closes) {
+                let (Some(date), Some(close)) = (date, close.and_then(parse_decimal)) else {
+                    continue;
+                };
+
+                levels.push(Quota {
+                    id: id.clone(),
+                    date: NaiveDate::parse_from_str(date.trim(), "%d/%m/%Y")?,
+                    value: close,
+                });
+            }
+        }
+
+        quota_returns(&levels, self.frequency)
+    }
+
+    fn frequency(&self) -> Frequency {
+        self.frequency
+    }
+}
+
+/// Daily reports of the CVM open data (`inf_diario_fi_YYYYMM.csv`). Monthly returns are
+/// taken between the last quotas of consecutive months.
+pub struct CvmDaily {
+    pub funds: Vec<Cnpj>,
+    pub frequency: Frequency,
+}
+
+impl Source for CvmDaily {
+    fn records(&self, paths: &[PathBuf]) -> Result<Vec<Record>> {
+        Ok(self.read(paths)?.records)
+    }
+
+    fn frequency(&self) -> Frequency {
+        self.frequency
+    }
+
+    fn read(&self, paths: &[PathBuf]) -> Result<SourceData> {
+        let mut all_quotas = Vec::new();
+        let mut skipped = Vec::new();
+
+        for path in paths {
+            if file_name(path)?.starts_with("inf_diario_fi_") {
+                let mut rows = Vec::new();
+                all_quotas.extend(quotas(&read_inf_diario(path)?, &self.funds, &mut rows)?);
+                skipped.extend(SkippedRow::from_file(path, rows));
+            }
+        }
+
+        Ok(SourceData {
+            records: quota_returns(&all_quotas, self.frequency)?,
+            fii_reports: Vec::new(),
+            skipped,
+        })
+    }
+}
+
+/// Monthly reports of FIIs of the CVM open data
+/// (`inf_mensal_fii_{geral,complemento,ativo_passivo}_YYYY.csv`). The general file of
+/// every year with a complement file is required, and the asset and liability one is
+/// optional.
+pub struct CvmFii {
+    pub funds: Vec<Cnpj>,
+}
+
+impl CvmFii {
+    /// Reports in the files, along with the rows skipped from the complement files.
+    pub fn reports(&self, paths: &[PathBuf]) -> Result<(Vec<FiiReport>, Vec<SkippedRow>)> {
+        let mut all_reports = Vec::new();
+        let mut skipped = Vec::new();
+
+        for path in paths {
+            let Some(year) = file_name(path)?
+                .strip_prefix("inf_mensal_fii_complemento_")
+                .and_then(|x| x.strip_suffix(".csv"))
+            else {
+                continue;
+            };
+
+            let sibling =
+                |kind: &str| path.with_file_name(format!("inf_mensal_fii_{}_{}.csv", kind, year));
+            let general_path = sibling("geral");
+            let balance_path = sibling("ativo_passivo");
+
+            let complement = read_inf_mensal(path)?;
+            let general = read_inf_mensal(&general_path)
+                .map_err(|e| anyhow!("Could not read {}: {}", general_path.display(), e))?;
+            let balance = if balance_path.exists() {
+                Some(read_inf_mensal(&balance_path)?)
+            } else {
+                None
+            };
+
+            let mut rows = Vec::new();
+            all_reports.extend(reports(
+                &general,
+                &complement,
+                balance.as_ref(),
+                &self.funds,
+                &mut rows,
+            )?);
+            rows.sort();
+            skipped.extend(SkippedRow::from_file(path, rows));
+        }
+
+        Ok((all_reports, skipped))
+    }
+}
+
+impl From<FiiReport> for Record {
+    fn from(report: FiiReport) -> Self {
+        Record {
+            id: report.cnpj.into(),
+            date: report.month,
+            value: Some(report.profitability),
+        }
+    }
+}
+
+impl Source for CvmFii {
+    fn records(&self, paths: &[PathBuf]) -> Result<Vec<Record>> {
+        Ok(self.read(paths)?.records)
+    }
+
+    fn frequency(&self) -> Frequency {
+        Frequency::Monthly
+    }
+
+    fn read(&self, paths: &[PathBuf]) -> Result<SourceData> {
+        let (fii_reports, skipped) = self.reports(paths)?;
+
+        Ok(SourceData {
+            records: fii_reports.iter().cloned().map(Record::from).collect(),
+            fii_reports,
+            skipped,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_dir;
+
+    /// Writes `files` to a fresh directory named after the test.
+    fn write(test: &str, files: &[(&str, &str)]) -> Vec<PathBuf> {
+        let dir = temp_dir(&format!("source_{}", test));
+
+        files
+            .iter()
+            .map(|(name, contents)| {
+                let path = dir.join(name);
+                std::fs::write(&path, contents).unwrap();
+                path
+            })
+            .collect()
+    }
+
+    fn date(y: i32, m: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, 1).unwrap()
+    }
+
+    #[test]
+    fn overlapping_sources_are_rejected() {
+        let source = |path: &str, frequency, months: &[u32]| SourceRecords {
+            path: path.to_string(),
+            frequency,
+            records: months
+                .iter()
+                .map(|m| Record {
+                    id: "11.111.111/0001-91".into(),
+                    date: date(2023, *m),
+                    value: Some(0.01),
+                })
+                .collect(),
+        };
+        let broker = source("broker", Frequency::Monthly, &[1, 2]);
+
+        assert!(check_overlaps(&[broker.clone(), source("cvm", Frequency::Monthly, &[3])]).is_ok());
+
+        let error = check_overlaps(&[broker.clone(), source("cvm", Frequency::Monthly, &[2, 3])])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("11.111.111/0001-91"));
+        assert!(error.contains("2023-02-01"));
+        assert!(error.contains("'broker'") && error.contains("'cvm'"));
+
+        let error = check_overlaps(&[broker, source("cvm", Frequency::Daily, &[3])])
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("monthly") && error.contains("daily"));
+    }
+
+    #[test]
+    fn broker_reads_cnpj_and_year_from_file_name() {
+        let paths = write(
+            "broker",
+            &[(
+                "17.453.850_0001-48_2022.csv",
+                ",Jan,Fev,Mar,Abr,Mai,Jun,Jul,Ago,Set,Out,Nov,Dez,Acumulado
+Fundo,\"-5,21\",\"-2,09\",\"4,58\",\"-8,78\",\"0,89\",\"-7,76\",\"10,11\",\"-3,22\",\"-8,61\",\"8,71\",\"6,01\",\"-4,84\",\"-11,99\"",
+            )],
+        );
+
+        let records = Broker.records(&paths).unwrap();
+
+        assert_eq!(records.len(), 12);
+        assert_eq!(records[0].id, "17.453.850/0001-48");
+        assert_eq!(records[0].date, date(2022, 1));
+        assert!((records[0].value.unwrap() + 0.0521).abs() < 1e-12);
+        assert_eq!(records[11].date, date(2022, 12));
+    }
+
+    #[test]
+    fn brasil_indicadores_keeps_placeholders_as_missing() {
+        let paths = write(
+            "brasil_indicadores",
+            &[(
+                "cdi.csv",
+                "Ano/Mês,Jan,Fev,Mar,Abr,Mai,Jun,Jul,Ago,Set,Out,Nov,Dez,Acumulado
+2024,\"0,97\",\"0,29\",---,---,---,---,---,---,---,---,---,---,\"1,26\"
+2023,\"1,12\",\"0,92\",\"1,17\",\"0,92\",\"1,12\",\"1,07\",\"1,07\",\"1,14\",\"0,97\",\"1,00\",\"0,92\",\"0,89\",\"13,04\"",
+            )],
+        );
+
+        let records = BrasilIndicadores.records(&paths).unwrap();
+
+        assert_eq!(records.len(), 24);
+        assert_eq!(records[0].id, "cdi");
+        assert_eq!(records[1].date, date(2024, 2));
+        assert!((records[1].value.unwrap() - 0.0029).abs() < 1e-12);
+        assert_eq!(records[2].value, None);
+        assert_eq!(records[12].date, date(2023, 1));
+        assert!((records[12].value.unwrap() - 0.0112).abs() < 1e-12);
+    }
+
+    #[test]
+    fn index_levels_compound_last_close_of_each_month() {
+        let paths = write(
+            "index_levels",
+            &[(
+                "ifix.csv",
+                "Data,Fechamento
+30/12/2022,\"2.000,00\"
+02/01/2023,\"2.010,00\"
+31/01/2023,\"2.050,00\"
+28/02/2023,\"2.009,00\"",
+            )],
+        );
+
+        let records = IndexLevels {
+            frequency: Frequency::Monthly,
+        }
+        .records(&paths)
+        .unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].id, "ifix");
+        assert_eq!(records[0].date, date(2023, 1));
+        assert!((records[0].value.unwrap() - 0.025).abs() < 1e-12);
+        assert!((records[1].value.unwrap() + 0.02).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cvm_daily_spans_files() {
+        let header = "TP_FUNDO;CNPJ_FUNDO;DT_COMPTC;VL_TOTAL;VL_QUOTA;VL_PATRIM_LIQ;CAPTC_DIA;RESG_DIA;NR_COTST";
+        let january = format!(
+            "{}\nFI;00.017.024/0001-53;2023-01-31;1000.0;1.00;1000.0;0;0;10",
+            header
+        );
+        // Files after CVM Resolution 175 rename the CNPJ column.
+        let february = format!(
+            "{}\nFI;00.017.024/0001-53;2023-02-28;1000.0;1.02;1000.0;0;0;10\nFI;00.017.024/0001-54;2023-02-28;1000.0;1.02;1000.0;0;0;10",
+            header.replace("CNPJ_FUNDO", "CNPJ_FUNDO_CLASSE")
+        );
+
+        let paths = write(
+            "cvm_daily",
+            &[
+                ("inf_diario_fi_202301.csv", &january),
+                ("inf_diario_fi_202302.csv", &february),
+                ("README.txt", "Not a report"),
+            ],
+        );
+
+        let SourceData {
+            records, skipped, ..
+        } = CvmDaily {
+            funds: Vec::new(),
+            frequency: Frequency::Monthly,
+        }
+        .read(&paths)
+        .unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].date, date(2023, 2));
+        assert!((records[0].value.unwrap() - 0.02).abs() < 1e-12);
+        assert_eq!(
+            skipped,
+            vec![SkippedRow {
+                file: paths[1].display().to_string(),
+                line: 3,
+                cnpj: "00.017.024/0001-54".to_string(),
+            }]
+        );
+
+        let records = CvmDaily {
+            funds: Vec::new(),
+            frequency: Frequency::Daily,
+        }
+        .records(&paths)
+        .unwrap();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].date,
+            NaiveDate::from_ymd_opt(2023, 2, 28).unwrap()
+        );
+    }
+
+    #[test]
+    fn cvm_fii_requires_general_file() {
+        let complement = "CNPJ_Fundo;Data_Referencia;Versao;Patrimonio_Liquido;Percentual_Rentabilidade_Efetiva_Mes;Percentual_Dividend_Yield_Mes
+11.111.111/0001-91;2023-01-01;1;1000000.0;0.010;0.008";
+        let general = "CNPJ_Fundo;Data_Referencia;Versao;Nome_Fundo
+11.111.111/0001-91;2023-01-01;1;FII UM";
+
+        let paths = write(
+            "cvm_fii",
+            &[
+                ("inf_mensal_fii_complemento_2023.csv", complement),
+                ("inf_mensal_fii_geral_2023.csv", general),
+            ],
+        );
+
+        let records = CvmFii { funds: Vec::new() }.records(&paths).unwrap();
+
+        assert_eq!(
+            records,
+            vec![Record {
+                id: "11.111.111/0001-91".into(),
+                date: date(2023, 1),
+                value: Some(0.01),
+            }]
+        );
+
+        std::fs::remove_file(&paths[1]).unwrap();
+        assert!(CvmFii { funds: Vec::new() }.records(&paths[..1]).is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::Calendar;
+use crate::cnpj::Cnpj;
+use crate::id::SeriesId;
+use crate::portfolio::TimeSeries;
+
+/// IOF charged on the gain of redemptions within the first 30 days, by days held.
+const IOF: [f64; 30] = [
+    1.00, 0.96, 0.93, 0.90, 0.86, 0.83, 0.80, 0.76, 0.73, 0.70, 0.66, 0.63, 0.60, 0.56, 0.53, 0.50,
+    0.46, 0.43, 0.40, 0.36, 0.33, 0.30, 0.26, 0.23, 0.20, 0.16, 0.13, 0.10, 0.06, 0.03,
+];
+
+/// Tax treatment of a fund for individuals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FundClass {
+    /// Fixed income or multimarket fund with long term portfolio: come-cotas of 15%
+    /// and IR from 22.5% down to 15% by holding period.
+    #[default]
+    LongTerm,
+    /// Fund with short term portfolio: come-cotas of 20% and IR of 22.5% or 20%.
+    ShortTerm,
+    /// Equity fund: 15% on the gain at redemption, without come-cotas nor IOF.
+    Equity,
+    /// Real estate fund (FII): dividends are exempt and the capital gain pays 20%.
+    RealEstate,
+    Exempt,
+}
+
+impl FundClass {
+    fn come_cotas_rate(&self) -> Option<f64> {
+        match self {
+            FundClass::LongTerm => Some(0.15),
+            FundClass::ShortTerm => Some(0.20),
+            _ => None,
+        }
+    }
+
+    /// Income tax rate on the gain of an amount redeemed after `days` calendar days.
+    pub fn income_tax_rate(&self, days: i64) -> f64 {
+        match self {
+            FundClass::LongTerm => match days {
+                ..=180 => 0.225,
+                181..=360 => 0.20,
+                361..=720 => 0.175,
+                _ => 0.15,
+            },
+            FundClass::ShortTerm => match days {
+                ..=180 => 0.225,
+                _ => 0.20,
+            },
+            FundClass::Equity => 0.15,
+            FundClass::RealEstate => 0.20,
+            FundClass::Exempt => 0.0,
+        }
+    }
+
+    /// IOF rate on the gain of an amount redeemed after `days` calendar days.
+    pub fn iof_rate(&self, days: i64) -> f64 {
+        match self {
+            FundClass::LongTerm | FundClass::ShortTerm => {
+                IOF.get(days.max(0) as usize).copied().unwrap_or(0.0)
+            }
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FundTaxation {
+    pub cnpj: Cnpj,
+    pub class: FundClass,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Taxes {
+    pub default_class: FundClass,
+    #[serde(default)]
+    pub funds: Vec<FundTaxation>,
+}
+
+impl Taxes {
+    pub fn class(&self, id: &SeriesId) -> FundClass {
+        id.cnpj()
+            .and_then(|cnpj| self.funds.iter().find(|f| f.cnpj == cnpj))
+            .map_or(self.default_class, |f| f.class)
+    }
+}
+
+/// Whether the come-cotas, charged on the last business days of May and November,
+/// falls within `start..end`.
+fn has_come_cotas(start: NaiveDate, end: NaiveDate) -> bool {
+    let calendar = Calendar::anbima();
+
+    (start.year()..=end.year()).any(|year| {
+        [5, 11].iter().any(|month| {
+            let date = NaiveDate::from_ymd_opt(year, *month, 1).expect("Dates are valid");
+            let date = calendar.last_business_day_of_month(date);
+
+            start <= date && date < end
+        })
+    })
+}
+
+/// Converts the gross returns of `gross` into returns net of taxes for an amount
+/// bought at the start of its first period and redeemed at the end of its last one,
+/// taxed according to `class`. Come-cotas is paid by reducing the quotas held, and the
+/// income tax complement and IOF are paid at the redemption, in the last period.
+///
+/// For real estate funds, `dividends` has the part of the returns of each period that
+/// was paid as exempt dividends, none for the periods missing from it. Without it, all
+/// of the return is taxed as capital gain.
+pub fn after_tax(
+    gross: &TimeSeries,
+    class: FundClass,
+    dividends: Option<&TimeSeries>,
+) -> Result<TimeSeries> {
+    let dividends = match dividends {
+        Some(d) => gross
+            .dates
+            .iter()
+            .map(|date| d.dates.binary_search(date).map_or(0.0, |i| d.returns[i]))
+            .collect(),
+        None => vec![0.0; gross.dates.len()],
+    };
+
+    let (Some(first), Some(last)) = (gross.dates.first(), gross.dates.last()) else {
+        return Err(anyhow!("Series '{}' has no periods", gross.id));
+    };
+    let redemption = gross.frequency.next_period(*last);
+    let days = (redemption - *first).num_days();
+
+    // Gross value of a quota, quota value when come-cotas was last paid, price of a
+    // quota without the dividends and number of quotas held.
+    let mut quota = 1.0;
+    let mut taxed_quota = 1.0;
+    let mut price = 1.0;
+    let mut quotas = 1.0;
+
+    let mut value = 1.0;
+    let mut returns = Vec::with_capacity(gross.returns.len());
+
+    for (i, (date, r)) in gross.dates.iter().zip(&gross.returns).enumerate() {
+        quota *= 1.0 + r;
+        price *= 1.0 + r - dividends[i];
+
+        let end = gross.frequency.next_period(*date);
+
+        if let Some(rate) = class.come_cotas_rate() {
+            if has_come_cotas(*date, end) && quota > taxed_quota {
+                quotas -= rate * quotas * (quota - taxed_quota) / quota;
+                taxed_quota = quota;
+            }
+        }
+
+        let mut value_at_end = quotas * quota;
+
+        if i + 1 == gross.returns.len() {
+            let gain = match class {
+                FundClass::RealEstate => quotas * (price - 1.0),
+                _ => quotas * (quota - 1.0),
+            };
+
+            if gain > 0.0 {
+                let iof = class.iof_rate(days) * gain;
+                let income_tax = class.income_tax_rate(days) * (gain - iof);
+                let already_paid =
+                    class.come_cotas_rate().unwrap_or(0.0) * quotas * (taxed_quota - 1.0);
+
+                value_at_end -= iof + (income_tax - already_paid).max(0.0);
+            }
+        }
+
+        returns.push(value_at_end / value - 1.0);
+        value = value_at_end;
+    }
+
+    Ok(
+        TimeSeries::new(gross.id.clone(), gross.dates.clone(), returns)?
+            .with_frequency(gross.frequency),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::monthly_from;
+
+    #[test]
+    fn regressive_table() {
+        assert_eq!(FundClass::LongTerm.income_tax_rate(30), 0.225);
+        assert_eq!(FundClass::LongTerm.income_tax_rate(181), 0.20);
+        assert_eq!(FundClass::LongTerm.income_tax_rate(720), 0.175);
+        assert_eq!(FundClass::LongTerm.income_tax_rate(721), 0.15);
+        assert_eq!(FundClass::ShortTerm.income_tax_rate(721), 0.20);
+        assert_eq!(FundClass::LongTerm.iof_rate(10), 0.66);
+        assert_eq!(FundClass::LongTerm.iof_rate(30), 0.0);
+    }
+
+    #[test]
+    fn come_cotas_and_complement() {
+        // Bought in January, with come-cotas at the end of May and redeemed at the end
+        // of June, so after 181 days.
+        let gross = monthly_from("a", (2023, 1), vec![0.01; 6]);
+        let net = after_tax(&gross, FundClass::LongTerm, None).unwrap();
+        let values = net.equity_curve(1.0);
+
+        assert!((values[0] - 1.01).abs() < 1e-12);
+
+        // Come-cotas takes 15% of the gain of 0.05101005 by the end of May, leaving
+        // 1.05101005 - 0.00765151 = 1.04335854.
+        assert!((values[4] - 1.04335854).abs() < 1e-8);
+
+        // June brings it to 1.05379213. The 0.99271985 quotas left have a gain of
+        // 0.06107227, taxed at 20% after 181 days, of which 0.00759580 was already paid
+        // by the come-cotas, so the complement is 0.01221445 - 0.00759580 = 0.00461865.
+        assert!((values[5] - (1.05379213 - 0.00461865)).abs() < 1e-8);
+    }
+
+    #[test]
+    fn iof_on_short_holdings() {
+        let gross = TimeSeries::new(
+            "a".to_string(),
+            vec![NaiveDate::from_ymd_opt(2023, 1, 2).unwrap()],
+            vec![0.01],
+        )
+        .unwrap()
+        .with_frequency(crate::portfolio::Frequency::Daily);
+
+        let net = after_tax(&gross, FundClass::LongTerm, None).unwrap();
+
+        let iof = 0.96 * 0.01;
+        let expected = 0.01 - iof - 0.225 * (0.01 - iof);
+
+        assert!((net.returns[0] - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn real_estate_dividends_are_exempt() {
+        let gross = monthly_from("a", (2023, 1), vec![0.01, 0.01]);
+        let dividends = monthly_from("a", (2023, 1), vec![0.01, 0.01]);
+
+        let net = after_tax(&gross, FundClass::RealEstate, Some(&dividends)).unwrap();
+
+        assert!((net.calculate_value_at_end(1.0) - 1.01 * 1.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn missing_dividends_are_taxed_as_gains() {
+        let gross = monthly_from("a", (2023, 1), vec![0.01, 0.01]);
+        let dividends = monthly_from("a", (2023, 2), vec![0.01]);
+
+        let net = after_tax(&gross, FundClass::RealEstate, Some(&dividends)).unwrap();
+
+        // Only the January return of 1% is taxed, at 20%.
+        let expected = 1.01 * 1.01 - 0.2 * 0.01;
+
+        assert!((net.calculate_value_at_end(1.0) - expected).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,516 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use statrs::statistics::Statistics;
+
+use crate::cnpj::Cnpj;
+use crate::source::{month_number, parse_percentage, read_table};
+
+/// Thresholds of the validation of the raw fund files copied from the broker.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Validation {
+    /// Largest accepted difference between the `Acumulado` column and the compounded
+    /// monthly returns, which are rounded to two decimal places.
+    pub accumulated_tolerance: f64,
+    /// Monthly returns further than this number of standard deviations from the average
+    /// of the fund are reported as outliers.
+    pub outlier_z_score: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Fails the preprocessing.
+    Error,
+    /// Reported, but possibly legitimate.
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    InvalidFileName,
+    InvalidCnpj,
+    YearMismatch,
+    DuplicateMonth,
+    AccumulatedMismatch,
+    Outlier,
+    MissingYear,
+}
+
+impl IssueKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            IssueKind::Outlier | IssueKind::MissingYear => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    pub file: String,
+    pub kind: IssueKind,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub files: Vec<String>,
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    fn push(&mut self, path: &Path, kind: IssueKind, message: String) {
+        self.issues.push(Issue {
+            file: path.display().to_string(),
+            kind,
+            severity: kind.severity(),
+            message,
+        });
+    }
+
+    pub fn errors(&self) -> Vec<&Issue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Error)
+            .collect()
+    }
+
+    /// Fails with every error found, one per line.
+    pub fn check(&self) -> Result<()> {
+        let errors = self.errors();
+
+        if errors.is_empty() {
+            return Ok(());
+        }
+
+        Err(anyhow!(
+            "{} errors found in the raw fund files:\n{}",
+            errors.len(),
+            errors
+                .iter()
+                .map(|e| format!("{}: {}", e.file, e.message))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ))
+    }
+}
+
+/// Monthly returns, accumulated return and row label of a file copied from the broker.
+struct BrokerFile {
+    path: PathBuf,
+    cnpj: Vec<u8>,
+    year: i32,
+    label: Option<String>,
+    returns: Vec<(u32, Option<f64>)>,
+    accumulated: Option<f64>,
+}
+
+fn read_broker_file(path: &Path, report: &mut ValidationReport) -> Result<Option<BrokerFile>> {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or(anyhow!("Invalid file name '{}'", path.display()))?;
+
+    let Some((cnpj, year)) = stem.rsplit_once('_') else {
+        report.push(
+            path,
+            IssueKind::InvalidFileName,
+            "File name should be '{CNPJ}_{YEAR}.csv'".to_string(),
+        );
+        return Ok(None);
+    };
+
+    let Ok(year) = year.parse::<i32>() else {
+        report.push(
+            path,
+            IssueKind::InvalidFileName,
+            format!("Invalid year '{}' in file name", year),
+        );
+        return Ok(None);
+    };
+
+    match cnpj.parse::<Cnpj>() {
+        Ok(parsed) if parsed.file_name() == cnpj => (),
+        Ok(_) => report.push(
+            path,
+            IssueKind::InvalidCnpj,
+            format!("CNPJ '{}' should be in the form 'XX.XXX.XXX_XXXX-XX'", cnpj),
+        ),
+        Err(e) => report.push(path, IssueKind::InvalidCnpj, e.to_string()),
+    }
+
+    // Files are still checked against the others of the same fund when only the format
+    // or the check digits of the CNPJ are wrong.
+    let digits: Vec<u8> = cnpj
+        .chars()
+        .filter_map(|c| c.to_digit(10).map(|d| d as u8))
+        .collect();
+    if digits.len() != 14 {
+        return Ok(None);
+    }
+
+    let df = read_table(path)?;
+
+    if df.height() == 0 {
+        return Err(anyhow!("No returns in '{}'", path.display()));
+    }
+
+    let mut returns = Vec::new();
+    let mut accumulated = None;
+
+    for column in df.get_columns() {
+        let value = column.str()?.get(0);
+
+        if column.name() == "Acumulado" {
+            accumulated = value.and_then(parse_percentage);
+        } else if let Ok(month) = month_number(column.name()) {
+            returns.push((month, value.and_then(parse_percentage)));
+        }
+    }
+
+    let label = df.get_columns()[0].str()?.get(0).map(str::to_string);
+
+    Ok(Some(BrokerFile {
+        path: path.to_path_buf(),
+        cnpj: digits,
+        year,
+        label,
+        returns,
+        accumulated,
+    }))
+}
+
+fn check_year(
+    file: &BrokerFile,
+    files: &[BrokerFile],
+    today: NaiveDate,
+    report: &mut ValidationReport,
+) {
+    if let Some(year) = file
+        .label
+        .as_ref()
+        .and_then(|l| l.trim().parse::<i32>().ok())
+    {
+        if year != file.year {
+            report.push(
+                &file.path,
+                IssueKind::YearMismatch,
+                format!(
+                    "Row is labeled {} but the file name has {}",
+                    year, file.year
+                ),
+            );
+        }
+    }
+
+    let future = file
+        .returns
+        .iter()
+        .filter(|(m, v)| v.is_some() && (file.year, *m) > (today.year(), today.month()))
+        .count();
+
+    if future > 0 {
+        report.push(
+            &file.path,
+            IssueKind::YearMismatch,
+            format!(
+                "{} months of {} after the current one have returns",
+                future, file.year
+            ),
+        );
+    }
+
+    let copy = files.iter().find(|other| {
+        other.cnpj == file.cnpj
+            && other.year != file.year
+            && other.returns == file.returns
+            && file.returns.iter().any(|(_, v)| v.is_some())
+    });
+
+    if let Some(copy) = copy {
+        report.push(
+            &file.path,
+            IssueKind::YearMismatch,
+            format!(
+                "Returns of {} are the same as the ones of {} in '{}'",
+                file.year,
+                copy.year,
+                copy.path.display()
+            ),
+        );
+    }
+}
+
+fn check_accumulated(file: &BrokerFile, tolerance: f64, report: &mut ValidationReport) {
+    let Some(accumulated) = file.accumulated else {
+        return;
+    };
+
+    let compounded = file
+        .returns
+        .iter()
+        .filter_map(|(_, v)| *v)
+        .fold(1.0, |acc, r| acc * (1.0 + r))
+        - 1.0;
+
+    if (compounded - accumulated).abs() > tolerance {
+        report.push(
+            &file.path,
+            IssueKind::AccumulatedMismatch,
+            format!(
+                "Accumulated return of {:.2}% doesn't match the {:.2}% of the compounded monthly returns",
+                100.0 * accumulated,
+                100.0 * compounded
+            ),
+        );
+    }
+}
+
+/// Checks the files of a fund together: months present in more than one file, monthly
+/// returns far from the average of the other ones of the fund and years missing between
+/// the first and last files.
+fn check_fund(files: &[&BrokerFile], z_score: f64, report: &mut ValidationReport) {
+    let mut months: BTreeMap<(i32, u32), Vec<&BrokerFile>> = BTreeMap::new();
+    for file in files {
+        for (month, _) in file.returns.iter().filter(|(_, v)| v.is_some()) {
+            months.entry((file.year, *month)).or_default().push(file);
+        }
+    }
+
+    for ((year, month), duplicates) in months.iter().filter(|(_, f)| f.len() > 1) {
+        for file in duplicates {
+            report.push(
+                &file.path,
+                IssueKind::DuplicateMonth,
+                format!(
+                    "Month {:02}/{} is in {} files of the same fund",
+                    month,
+                    year,
+                    duplicates.len()
+                ),
+            );
+        }
+    }
+
+    let returns: Vec<(&BrokerFile, u32, f64)> = files
+        .iter()
+        .flat_map(|f| {
+            f.returns
+                .iter()
+                .filter_map(move |(m, v)| v.map(|v| (*f, *m, v)))
+        })
+        .collect();
+
+    for (i, (file, month, r)) in returns.iter().enumerate() {
+        let others: Vec<f64> = returns
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, (_, _, r))| *r)
+            .collect();
+
+        if others.len() < 2 {
+            continue;
+        }
+
+        let (mean, std) = (others.iter().mean(), others.iter().std_dev());
+
+        if std > 0.0 && ((r - mean) / std).abs() > z_score {
+            report.push(
+                &file.path,
+                IssueKind::Outlier,
+                format!(
+                    "Return of {:.2}% in {:02}/{} is more than {} standard deviations from the average of the fund",
+                    100.0 * r,
+                    month,
+                    file.year,
+                    z_score
+                ),
+            );
+        }
+    }
+
+    let years: BTreeSet<i32> = files.iter().map(|f| f.year).collect();
+    for (previous, next) in years.iter().zip(years.iter().skip(1)) {
+        if next - previous > 1 {
+            let last = files
+                .iter()
+                .find(|f| f.year == *next)
+                .expect("Year comes from one of the files");
+
+            report.push(
+                &last.path,
+                IssueKind::MissingYear,
+                format!(
+                    "Years {} to {} are missing before {}",
+                    previous + 1,
+                    next - 1,
+                    next
+                ),
+            );
+        }
+    }
+}
+
+/// Validates the files copied from the broker, named `{CNPJ}_{YEAR}.csv`, reporting
+/// every issue found. Months after `today` are expected to be empty.
+pub fn validate_broker_files(
+    paths: &[PathBuf],
+    validation: &Validation,
+    today: NaiveDate,
+) -> Result<ValidationReport> {
+    let mut report = ValidationReport {
+        files: paths.iter().map(|p| p.display().to_string()).collect(),
+        issues: Vec::new(),
+    };
+
+    let mut files = Vec::new();
+    for path in paths {
+        if let Some(file) = read_broker_file(path, &mut report)? {
+            files.push(file);
+        }
+    }
+
+    for file in &files {
+        check_year(file, &files, today, &mut report);
+        check_accumulated(file, validation.accumulated_tolerance, &mut report);
+    }
+
+    let mut funds: BTreeMap<&[u8], Vec<&BrokerFile>> = BTreeMap::new();
+    for file in &files {
+        funds.entry(&file.cnpj).or_default().push(file);
+    }
+
+    for fund in funds.values() {
+        check_fund(fund, validation.outlier_z_score, &mut report);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::temp_dir;
+
+    const HEADER: &str = ",Jan,Fev,Mar,Abr,Mai,Jun,Jul,Ago,Set,Out,Nov,Dez,Acumulado";
+
+    fn validation() -> Validation {
+        Validation {
+            accumulated_tolerance: 0.001,
+            outlier_z_score: 3.0,
+        }
+    }
+
+    fn write(dir: &Path, name: &str, row: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, format!("{}\n{}", HEADER, row)).unwrap();
+        path
+    }
+
+    #[test]
+    fn reports_issues_of_broker_files() {
+        let dir = temp_dir("validation_broker");
+
+        let ones = r#"Fundo,"1,00","1,00","1,00","1,00","1,00","1,00","1,00","1,00","1,00","1,00","1,00","1,00""#;
+        let twos = ones.replace("1,00", "2,00");
+        let paths = vec![
+            write(
+                &dir,
+                "32.319.351_0001-56_2020.csv",
+                &format!("{},\"12,68\"", ones),
+            ),
+            // Same returns as 2020 and a wrong accumulated return.
+            write(
+                &dir,
+                "32.319.351_0001-56_2021.csv",
+                &format!("{},\"10,00\"", ones),
+            ),
+            // 2022 is missing.
+            write(&dir, "32.319.351_0001-56_2023.csv", &format!("{},", twos)),
+            write(&dir, "32.319.351_0001-57_2021.csv", &format!("{},", twos)),
+            write(&dir, "32319351000156_2023.csv", &format!("{},", twos)),
+        ];
+
+        let today = NaiveDate::from_ymd_opt(2023, 6, 15).unwrap();
+        let report = validate_broker_files(&paths, &validation(), today).unwrap();
+        let count = |kind| report.issues.iter().filter(|i| i.kind == kind).count();
+
+        assert_eq!(count(IssueKind::AccumulatedMismatch), 1);
+        assert_eq!(count(IssueKind::MissingYear), 1);
+        assert_eq!(count(IssueKind::InvalidCnpj), 2);
+        assert!(report
+            .issues
+            .iter()
+            .any(|i| i.message == "CNPJ '32.319.351_0001-57' has invalid check digits"));
+        // Every month of 2023 is in both files of it.
+        assert_eq!(count(IssueKind::DuplicateMonth), 24);
+        // 2020 and 2021 have the same returns, and both files of 2023 have returns
+        // after June.
+        assert_eq!(count(IssueKind::YearMismatch), 4);
+        assert!(report.check().is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_duplicates_across_sources() {
+        let dir = temp_dir("validation_sources");
+        let (first, second) = (dir.join("first"), dir.join("second"));
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+
+        let row = r#"2021,"1,00","1,10","0,90","1,00","1,05","0,95","1,00","1,00","1,00","1,00","1,00","1,00","#;
+        let paths = vec![
+            write(&first, "21.185.984_0001-00_2021.csv", row),
+            write(&second, "21.185.984_0001-00_2021.csv", row),
+        ];
+
+        let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let report = validate_broker_files(&paths, &validation(), today).unwrap();
+
+        assert_eq!(
+            report
+                .issues
+                .iter()
+                .filter(|i| i.kind == IssueKind::DuplicateMonth)
+                .count(),
+            24
+        );
+        assert!(report.check().is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reports_outliers_as_warnings() {
+        let dir = temp_dir("validation_outliers");
+
+        let row = r#"2021,"1,00","1,10","0,90","1,00","1,05","0,95","1,00","1,00","25,00","1,00","1,00","1,00","#;
+        let paths = vec![
+            write(&dir, "21.185.984_0001-00_2021.csv", row),
+            write(&dir, "21.185.984_0001-00_2022.csv", row),
+        ];
+
+        let today = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let report = validate_broker_files(&paths, &validation(), today).unwrap();
+        let count = |kind| report.issues.iter().filter(|i| i.kind == kind).count();
+
+        assert_eq!(count(IssueKind::Outlier), 2);
+        // The file of 2022 is labeled 2021, and both have the same returns.
+        assert_eq!(count(IssueKind::YearMismatch), 3);
+
+        let report = validate_broker_files(&paths[..1], &validation(), today).unwrap();
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].severity, Severity::Warning);
+        assert!(report.check().is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}